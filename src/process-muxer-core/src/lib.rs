@@ -1,4 +1,10 @@
 pub(crate) mod muxer;
+pub use muxer::process::signal::Signal;
+pub use muxer::{
+    Cgroup, ChildHandle, ChildInfo, ChildStatus, CoreDumps, CrashLoop, Deadline, Event, FdTag,
+    Focus, HealthCheck, Identity, Limit, Listener, Muxer, Pid, Resource, ResourceLimits, Restart,
+    RestartPolicy, Retention, Rlimit, RunState, SpawnOptions, StdioMode, Stop, StopMethod,
+    StreamStatus, TailLine, Termination, TimerId, Watchdog, WatchdogAction,
+};
 #[cfg(feature = "signals")]
-pub use muxer::source::signal::Signal;
-pub use muxer::{ChildInfo, Event, FdTag, Muxer, Pid};
+pub use muxer::{ShutdownStage, SignalRule, SignalTarget};
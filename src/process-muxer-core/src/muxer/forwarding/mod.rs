@@ -0,0 +1,100 @@
+use std::io;
+
+use crate::muxer::notice::Notice;
+use crate::muxer::source::signal::SignalSource;
+use crate::muxer::{EventSource, Muxer, State};
+use crate::Signal;
+
+/// The children a `SignalRule` sends to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignalTarget {
+    All,
+    /// Children with this label.
+    Label(String),
+}
+
+/// Forward a signal the muxer receives to some of its children, possibly
+/// as a different signal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignalRule {
+    pub signal: Signal,
+    pub target: SignalTarget,
+    /// The signal sent to the children, the received one by default.
+    pub send: Signal,
+}
+
+impl SignalRule {
+    pub fn new(signal: Signal, target: SignalTarget) -> Self {
+        Self {
+            signal,
+            target,
+            send: signal,
+        }
+    }
+
+    /// Send `send` to the children instead of the received signal, e.g.
+    /// `SIGTERM` for `SIGINT`.
+    pub fn translate(mut self, send: Signal) -> Self {
+        self.send = send;
+        self
+    }
+}
+
+impl Muxer {
+    /// Forward signals matching `rule` to children. The muxer starts
+    /// listening for the signal if it did not already. A signal that matches
+    /// any rule is reported as `Event::SignalForwarded` for each child it was
+    /// sent to, instead of as `Event::SignalReceived`.
+    pub fn add_signal_rule(&mut self, rule: SignalRule) -> io::Result<()> {
+        let signum = rule.signal.as_raw();
+        if signal_hook::consts::FORBIDDEN.contains(&signum) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} cannot be handled", rule.signal),
+            ));
+        }
+        if let Some(source) = self.signal_source() {
+            source.add_signal(signum)?;
+        }
+        self.signal_rules.push(rule);
+        Ok(())
+    }
+
+    /// Remove every `SignalRule`. Signals the muxer started listening for
+    /// are reported as `Event::SignalReceived` from then on.
+    pub fn clear_signal_rules(&mut self) {
+        self.signal_rules.clear();
+    }
+
+    fn signal_source(&mut self) -> Option<&mut SignalSource> {
+        if let State::DrainingSignals(source) = &mut self.state {
+            return Some(source);
+        }
+        self.fds.iter_mut().find_map(|(_, source)| match source {
+            EventSource::ReceivedSignal(source) => Some(source),
+            _ => None,
+        })
+    }
+
+    /// Apply the rules for `signal`. Returns false if there are none.
+    pub(crate) fn forward_signal(&mut self, signal: Signal) -> bool {
+        let mut matched = false;
+        for rule in self.signal_rules.iter().filter(|r| r.signal == signal) {
+            matched = true;
+            for child in self.children.values() {
+                let state = &child.handle.state;
+                let targeted = match &rule.target {
+                    SignalTarget::All => true,
+                    SignalTarget::Label(label) => *label == *state.label,
+                };
+                if targeted && child.handle.signal(rule.send).is_ok() {
+                    self.notices.push_back(Notice::SignalForwarded {
+                        child: state.clone(),
+                        signal: rule.send,
+                    });
+                }
+            }
+        }
+        matched
+    }
+}
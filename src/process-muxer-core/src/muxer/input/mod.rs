@@ -0,0 +1,251 @@
+use std::{
+    io::{self, ErrorKind, Write},
+    os::fd::AsRawFd,
+    process::ChildStdin,
+};
+
+use mio::{event::Source, Interest, Token};
+
+use crate::muxer::notice::Notice;
+use crate::muxer::source::stdin::StdinSource;
+use crate::muxer::source::SourceInstruction;
+use crate::muxer::{EventSource, Muxer};
+
+/// Where lines read from the muxer's stdin are sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Focus {
+    /// Lines are not sent anywhere.
+    #[default]
+    None,
+    /// Lines are sent to children with this label.
+    Child(String),
+    /// Lines are sent to every child.
+    All,
+}
+
+/// Stdin forwarding settings, once enabled.
+#[derive(Debug)]
+pub(crate) struct Input {
+    pub(crate) focus: Focus,
+    /// Lines starting with this are commands to the muxer.
+    command_prefix: String,
+    /// Stdin while it is the terminal and a foreground child owns it, since
+    /// reading it from the background would stop the muxer with `SIGTTIN`.
+    parked: Option<StdinSource>,
+}
+
+/// A child's stdin kept by the muxer. It is written without blocking, so that
+/// a child that stops reading cannot stall `pump`.
+#[derive(Debug)]
+pub(crate) struct KeptStdin {
+    pipe: ChildStdin,
+    /// The rest of a partially written line.
+    pending: Vec<u8>,
+}
+
+impl KeptStdin {
+    pub(crate) fn new(pipe: ChildStdin) -> io::Result<Self> {
+        set_nonblocking(&pipe, true)?;
+        Ok(Self {
+            pipe,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Send `data` once what is pending has been written, buffering what the
+    /// pipe does not take. Returns false, sending nothing, if the child has
+    /// not read what was sent before.
+    pub(crate) fn send(&mut self, data: &[u8]) -> io::Result<bool> {
+        if !self.flush_pending()? {
+            return Ok(false);
+        }
+        self.pending.extend_from_slice(data);
+        self.flush_pending()?;
+        Ok(true)
+    }
+
+    /// Write as much of what is pending as the pipe takes. Returns true if
+    /// nothing is left.
+    fn flush_pending(&mut self) -> io::Result<bool> {
+        while !self.pending.is_empty() {
+            match self.pipe.write(&self.pending) {
+                Ok(n) => drop(self.pending.drain(..n)),
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => return Ok(false),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    /// The pipe, blocking again. Anything pending is discarded.
+    pub(crate) fn into_pipe(self) -> io::Result<ChildStdin> {
+        set_nonblocking(&self.pipe, false)?;
+        Ok(self.pipe)
+    }
+}
+
+fn set_nonblocking(pipe: &ChildStdin, nonblocking: bool) -> io::Result<()> {
+    let fd = pipe.as_raw_fd();
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    let flags = match nonblocking {
+        true => flags | libc::O_NONBLOCK,
+        false => flags & !libc::O_NONBLOCK,
+    };
+    if unsafe { libc::fcntl(fd, libc::F_SETFL, flags) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+impl Muxer {
+    /// Read the muxer's stdin and send each line to the children in focus.
+    /// Only children spawned with `SpawnOptions::stdin_input` receive input.
+    ///
+    /// Lines starting with `command_prefix` change the focus instead:
+    /// `{prefix}focus <label>`, `{prefix}all` and `{prefix}none`. Stdin must
+    /// be a pipe or a terminal that the muxer can read from; a foreground
+    /// child owning the terminal takes its input directly.
+    pub fn forward_stdin(&mut self, command_prefix: impl Into<String>) -> io::Result<()> {
+        if self.input.is_none() {
+            let mut source = EventSource::Stdin(StdinSource::new());
+            let entry = self.fds.vacant_entry();
+            source.register(self.poll.registry(), Token(entry.key()), Interest::READABLE)?;
+            entry.insert(source);
+        }
+        let (focus, parked) = match self.input.take() {
+            Some(input) => (input.focus, input.parked),
+            None => (Focus::default(), None),
+        };
+        self.input = Some(Input {
+            focus,
+            command_prefix: command_prefix.into(),
+            parked,
+        });
+        Ok(())
+    }
+
+    /// Change where stdin is forwarded to.
+    pub fn set_focus(&mut self, focus: Focus) {
+        if let Some(input) = &mut self.input {
+            if input.focus != focus {
+                input.focus = focus.clone();
+                self.notices.push_back(Notice::FocusChanged { focus });
+            }
+        }
+    }
+
+    /// Where stdin is forwarded to, if forwarding is enabled.
+    pub fn focus(&self) -> Option<&Focus> {
+        self.input.as_ref().map(|input| &input.focus)
+    }
+
+    /// Read stdin and route what was read. If stdin is the terminal and a
+    /// foreground child owns it, it is parked instead until the muxer takes
+    /// the terminal back.
+    pub(crate) fn stdin_ready(&mut self, mut source: StdinSource) {
+        if self.terminal_in_use() && unsafe { libc::isatty(libc::STDIN_FILENO) } == 1 {
+            source.deregister(self.poll.registry()).unwrap();
+            if let Some(input) = &mut self.input {
+                input.parked = Some(source);
+            }
+            return;
+        }
+        let mut lines = Vec::new();
+        let instruction = source.read_lines(&mut lines);
+        for line in lines {
+            self.route_line(line);
+        }
+        match instruction {
+            SourceInstruction::Reregister => self.reregister(EventSource::Stdin(source)),
+            SourceInstruction::Deregister => {
+                self.deregister(EventSource::Stdin(source));
+                self.close_focused_stdin();
+            }
+        }
+    }
+
+    /// Listen on stdin again after the muxer took the terminal back. Input
+    /// that arrived in the meantime is reported as soon as it is registered.
+    pub(crate) fn resume_stdin(&mut self) {
+        if self.terminal_in_use() {
+            return;
+        }
+        if let Some(source) = self.input.as_mut().and_then(|i| i.parked.take()) {
+            self.register(EventSource::Stdin(source));
+        }
+    }
+
+    fn route_line(&mut self, line: String) {
+        let Some(input) = &self.input else {
+            return;
+        };
+        if let Some(command) = line.strip_prefix(input.command_prefix.as_str()) {
+            let command = command.trim();
+            let focus = match command.split_once(' ') {
+                Some(("focus", label)) => Some(Focus::Child(label.trim().to_owned())),
+                _ if command == "all" => Some(Focus::All),
+                _ if command == "none" => Some(Focus::None),
+                _ => None,
+            };
+            match focus {
+                Some(focus) => self.set_focus(focus),
+                None => self
+                    .notices
+                    .push_back(Notice::StdinUnrouted { line, child: None }),
+            }
+            return;
+        }
+
+        let focus = input.focus.clone();
+        let mut data = line.clone().into_bytes();
+        data.push(b'\n');
+        let mut delivered = false;
+        for child in self.children.values_mut() {
+            if !child.spec.options.stdin_input || !focused(&focus, &child.handle.state.label) {
+                continue;
+            }
+            let Some(stdin) = &mut child.stdin else {
+                continue;
+            };
+            match stdin.send(&data) {
+                Ok(true) => delivered = true,
+                // The child is not reading, so the line is dropped rather
+                // than buffered without bound.
+                Ok(false) => self.notices.push_back(Notice::StdinUnrouted {
+                    line: line.clone(),
+                    child: Some(child.handle.state.clone()),
+                }),
+                // The child closed its stdin.
+                Err(_) => child.stdin = None,
+            }
+        }
+        if !delivered {
+            self.notices
+                .push_back(Notice::StdinUnrouted { line, child: None });
+        }
+    }
+
+    /// Pass the end of the muxer's stdin on to the children in focus.
+    fn close_focused_stdin(&mut self) {
+        let Some(input) = &self.input else {
+            return;
+        };
+        for child in self.children.values_mut() {
+            if child.spec.options.stdin_input && focused(&input.focus, &child.handle.state.label) {
+                child.stdin = None;
+            }
+        }
+    }
+}
+
+fn focused(focus: &Focus, label: &str) -> bool {
+    match focus {
+        Focus::None => false,
+        Focus::Child(focused) => focused == label,
+        Focus::All => true,
+    }
+}
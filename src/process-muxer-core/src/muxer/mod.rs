@@ -1,16 +1,61 @@
-mod process;
+#[cfg(feature = "signals")]
+mod forwarding;
+mod input;
+mod notice;
+pub(crate) mod process;
+#[cfg(feature = "signals")]
+mod shutdown;
 pub(crate) mod source;
+mod timer;
+#[cfg(feature = "signals")]
+pub use forwarding::{SignalRule, SignalTarget};
+pub use input::Focus;
+use input::{Input, KeptStdin};
+use notice::Notice;
+use process::activation;
+pub use process::activation::Listener;
+pub use process::cgroup::Cgroup;
+use process::cgroup::CgroupDir;
+pub use process::coredump::CoreDumps;
+pub use process::deadline::Deadline;
+use process::foreground::Terminal;
+pub use process::handle::ChildHandle;
+use process::handle::ChildState;
+pub use process::health::HealthCheck;
+use process::health::{HealthProbe, HealthState};
+use process::history::OutputTail;
+pub use process::history::{Retention, TailLine};
+use process::identity;
+pub use process::identity::Identity;
+use process::limits;
+pub use process::limits::{Limit, Rlimit};
+use process::options::ChildSpec;
+pub use process::options::SpawnOptions;
+pub use process::options::StdioMode;
 pub use process::pid::Pid;
+use process::resources::ResourceState;
+pub use process::resources::{Resource, ResourceLimits};
+pub use process::restart::{CrashLoop, Restart, RestartPolicy};
+pub use process::status::{ChildStatus, RunState, StreamStatus, Termination};
+use process::stop::StopCommand;
+pub use process::stop::{Stop, StopMethod};
+pub use process::watchdog::{Watchdog, WatchdogAction};
+#[cfg(feature = "signals")]
+pub use shutdown::ShutdownStage;
 use source::termination::ChildTerminationSource;
 use std::{
-    cell::Cell,
-    collections::BTreeMap,
+    collections::{BTreeMap, VecDeque},
+    ffi::OsString,
     io::{self, BufRead, ErrorKind},
     mem,
+    os::unix::process::CommandExt,
     path::{Path, PathBuf},
-    process::{Child, ChildStdin, Command, ExitStatus},
-    rc::Rc,
+    process::{ChildStdin, Command, ExitStatus},
+    rc::{Rc, Weak},
+    time::{Duration, Instant, SystemTime},
 };
+pub use timer::TimerId;
+use timer::{TimerKey, Timers};
 
 use mio::{
     event::{self, Source},
@@ -19,28 +64,56 @@ use mio::{
 use slab::Slab;
 
 pub use self::source::childout::FdTag;
-use self::source::{childout::ChildOut, SourceInstruction};
-
-#[cfg(feature = "signals")]
-use self::source::EventStream;
+use self::source::notify::{NotifySocket, NotifySource};
+use self::source::stdin::StdinSource;
+use self::source::{childout::ChildOut, EventStream, SourceInstruction};
+use crate::Signal;
 #[cfg(feature = "signals")]
-use source::signal::{Signal, SignalSource};
+use source::signal::SignalSource;
 
-/// A handle to a child process that was spawned with `Muxer`.
+/// How long `Muxer::restart_child` waits for a child without a `Stop::grace`
+/// to exit before sending `SIGKILL`.
+const RESTART_GRACE: Duration = Duration::from_secs(10);
+
+/// A child process that was spawned with `Muxer`.
 pub struct ChildInfo {
     pub pid: Pid,
     pub stdin: Option<ChildStdin>,
-    prog_path: Rc<PathBuf>,
-    exit_status: Rc<Cell<Option<ExitStatus>>>,
+    handle: ChildHandle,
 }
 
 impl ChildInfo {
     pub fn program(&self) -> &Path {
-        &self.prog_path
+        self.handle.program()
+    }
+
+    pub fn label(&self) -> &str {
+        self.handle.label()
+    }
+
+    pub fn args(&self) -> &[OsString] {
+        self.handle.args()
+    }
+
+    pub fn current_dir(&self) -> Option<&Path> {
+        self.handle.current_dir()
+    }
+
+    pub fn envs(&self) -> &[(OsString, Option<OsString>)] {
+        self.handle.envs()
+    }
+
+    pub fn started_at(&self) -> Instant {
+        self.handle.started_at()
     }
 
     pub fn exit_status(&self) -> Option<ExitStatus> {
-        self.exit_status.get()
+        self.handle.exit_status()
+    }
+
+    /// A cloneable handle for signalling and waiting on the child.
+    pub fn handle(&self) -> &ChildHandle {
+        &self.handle
     }
 }
 
@@ -49,22 +122,145 @@ impl ChildInfo {
 pub enum Event<'a> {
     ChildTerminated {
         pid: Pid,
+        label: &'a str,
         prog_path: &'a Path,
         exit_status: ExitStatus,
+        /// How long the child ran.
+        runtime: Duration,
+        /// The core file the child dumped, if it did and it could be found.
+        core_dump: Option<&'a Path>,
     },
+    /// A child was stopped by `signal`, e.g. `SIGTSTP` from Ctrl-Z. The muxer
+    /// takes the terminal back from a foreground child that stops.
+    ChildStopped {
+        pid: Pid,
+        label: &'a str,
+        signal: Signal,
+    },
+    /// A stopped child was continued.
+    ChildContinued { pid: Pid, label: &'a str },
     ChildWrote {
         pid: Pid,
+        label: &'a str,
         prog_path: &'a Path,
         tag: FdTag,
         line: &'a str,
     },
     FdClosed {
         pid: Pid,
+        label: &'a str,
         prog_path: &'a Path,
         tag: FdTag,
     },
+    /// A child exited and will be spawned again after `delay`, as its
+    /// `RestartPolicy` requires. `pid` is the pid of the exited child.
+    ChildRestarting {
+        pid: Pid,
+        label: &'a str,
+        attempt: u32,
+        delay: Duration,
+    },
+    /// A child was spawned again, replacing the child with pid `previous`.
+    ChildRestarted {
+        pid: Pid,
+        label: &'a str,
+        previous: Pid,
+    },
+    /// Spawning a child again failed. It will not be retried.
+    RestartFailed {
+        pid: Pid,
+        label: &'a str,
+        error: &'a io::Error,
+    },
+    /// A child failed too often within its `CrashLoop` window and will not
+    /// be restarted. `stderr` holds its last lines of stderr, oldest first.
+    CrashLoop {
+        pid: Pid,
+        label: &'a str,
+        failures: u32,
+        stderr: &'a [String],
+    },
+    /// A child's `HealthCheck` failed too many times in a row, or passed again
+    /// after that.
+    HealthChanged {
+        pid: Pid,
+        label: &'a str,
+        healthy: bool,
+    },
+    /// A child spawned with `SpawnOptions::notify` sent a datagram to its
+    /// `NOTIFY_SOCKET`, e.g. `READY=1` or `STATUS=...`.
+    ChildNotified {
+        pid: Pid,
+        label: &'a str,
+        fields: &'a [(String, String)],
+    },
+    /// A child with a `Watchdog` wrote nothing and sent no `WATCHDOG=1` for
+    /// `silent`. Its `WatchdogAction` has been taken.
+    WatchdogExpired {
+        pid: Pid,
+        label: &'a str,
+        silent: Duration,
+    },
+    /// A child ran for longer than its `Deadline` and has been sent
+    /// `SIGTERM`. Its `ChildTerminated` follows.
+    DeadlineExceeded {
+        pid: Pid,
+        label: &'a str,
+        runtime: Duration,
+    },
+    /// A child with `ResourceLimits` was sampled. `rss` is in bytes and `cpu`
+    /// is in cores, averaged since the previous sample.
+    ResourceSample {
+        pid: Pid,
+        label: &'a str,
+        rss: u64,
+        cpu: f64,
+    },
+    /// A child went over one of its `ResourceLimits` and has been killed.
+    ResourceLimitExceeded {
+        pid: Pid,
+        label: &'a str,
+        resource: Resource,
+    },
+    /// The kernel's OOM killer killed `kills` processes in the cgroup of a
+    /// child placed with `SpawnOptions::cgroup` while it ran. Follows its
+    /// `ChildTerminated`.
+    OomKilled {
+        pid: Pid,
+        label: &'a str,
+        kills: u64,
+    },
+    /// The focus of stdin forwarding changed, through `Muxer::set_focus` or a
+    /// command read from stdin.
+    FocusChanged { focus: &'a Focus },
+    /// A line read from the muxer's stdin was not delivered to any child,
+    /// or was an unknown command. With a label, the line was dropped for that
+    /// child because it is not reading its stdin.
+    StdinUnrouted {
+        line: &'a str,
+        label: Option<&'a str>,
+    },
+    /// The `StopMethod::Command` of a child exited unsuccessfully, or, with
+    /// no exit status, ran too long and was killed.
+    StopCommandFailed {
+        pid: Pid,
+        label: &'a str,
+        exit_status: Option<ExitStatus>,
+    },
+    /// A timer set with `Muxer::set_timer` expired.
+    TimerFired { id: TimerId },
     #[cfg(feature = "signals")]
     SignalReceived { signal: Signal },
+    /// A shutdown started with `Muxer::begin_shutdown` reached `stage`.
+    #[cfg(feature = "signals")]
+    Shutdown { stage: ShutdownStage },
+    /// A received signal was sent to a child according to a `SignalRule`.
+    #[cfg(feature = "signals")]
+    SignalForwarded {
+        pid: Pid,
+        label: &'a str,
+        signal: Signal,
+    },
 }
 
 /// A process Muxer
@@ -74,7 +270,32 @@ pub struct Muxer {
     children: BTreeMap<Pid, MuxerChild>,
     fds: Slab<EventSource>,
     state: State,
-    wait_buffer: Vec<(Pid, Rc<PathBuf>, ExitStatus)>,
+    wait_buffer: Vec<MuxerChild>,
+    retention: Retention,
+    history: VecDeque<ChildHandle>,
+    notices: VecDeque<Notice>,
+    timers: Timers<Timeout>,
+    restarts_enabled: bool,
+    health_probes: BTreeMap<Pid, HealthProbe>,
+    /// Commands run by `stop_child`, until they exit.
+    stop_commands: Vec<StopCommand>,
+    #[cfg(feature = "signals")]
+    signal_rules: Vec<SignalRule>,
+    /// The stage of the shutdown in progress, see `begin_shutdown`.
+    #[cfg(feature = "signals")]
+    shutdown: Option<ShutdownStage>,
+    /// Stdin forwarding, once enabled with `forward_stdin`.
+    input: Option<Input>,
+    /// The controlling terminal, once a foreground child has been spawned.
+    terminal: Option<Terminal>,
+    /// Core files located for children and their modification times, oldest
+    /// first.
+    core_dumps: VecDeque<(PathBuf, SystemTime)>,
+    /// Cgroups shared through `Cgroup::group`, by path.
+    cgroups: BTreeMap<PathBuf, Weak<CgroupDir>>,
+    /// Killed cgroups waiting to empty so that they can be removed, and when
+    /// to give up on them.
+    dead_cgroups: Vec<(PathBuf, Instant)>,
     // We don't need this field, an index into "events" would do, but the Events
     // type only exposes an iterator over references
     pending_events: Vec<event::Event>,
@@ -85,6 +306,22 @@ impl Muxer {
         let mut res = Self {
             poll: Poll::new()?,
             wait_buffer: Vec::new(),
+            retention: Retention::default(),
+            history: VecDeque::new(),
+            notices: VecDeque::new(),
+            timers: Timers::new(),
+            restarts_enabled: true,
+            health_probes: BTreeMap::new(),
+            stop_commands: Vec::new(),
+            cgroups: BTreeMap::new(),
+            dead_cgroups: Vec::new(),
+            core_dumps: VecDeque::new(),
+            terminal: None,
+            input: None,
+            #[cfg(feature = "signals")]
+            signal_rules: Vec::new(),
+            #[cfg(feature = "signals")]
+            shutdown: None,
             events: Events::with_capacity(1024),
             children: BTreeMap::new(),
             fds: Slab::new(),
@@ -106,45 +343,383 @@ impl Muxer {
         self.children.keys()
     }
 
-    pub fn spawn(&mut self, mut cmd: Command) -> io::Result<ChildInfo> {
+    /// Configure how many exited children are remembered, and how many lines
+    /// of output are kept per child. The tail length only applies to children
+    /// spawned afterwards.
+    pub fn set_retention(&mut self, retention: Retention) {
+        self.retention = retention;
+        self.trim_history();
+        self.trim_core_dumps();
+    }
+
+    /// A handle to the child with the given pid, if it is still running or
+    /// retained in the history.
+    pub fn child(&self, pid: Pid) -> Option<ChildHandle> {
+        match self.children.get(&pid) {
+            Some(c) => Some(c.handle.clone()),
+            None => self.history.iter().rev().find(|h| h.pid() == pid).cloned(),
+        }
+    }
+
+    /// Handles to retained exited children, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &ChildHandle> {
+        self.history.iter()
+    }
+
+    /// A snapshot of every running child followed by the retained exited
+    /// children.
+    pub fn status(&self) -> Vec<ChildStatus> {
+        let running = self.children.values().map(|c| &c.handle);
+        running
+            .chain(self.history.iter())
+            .map(|h| h.status())
+            .collect()
+    }
+
+    fn remember(&mut self, handle: ChildHandle) {
+        self.history.push_back(handle);
+        self.trim_history();
+    }
+
+    fn trim_history(&mut self) {
+        while self.history.len() > self.retention.children {
+            self.history.pop_front();
+        }
+    }
+
+    /// Handles to all children that have not yet been reaped.
+    pub fn handles(&self) -> impl Iterator<Item = ChildHandle> + '_ {
+        self.children.values().map(|c| c.handle.clone())
+    }
+
+    /// Take the stdin of a child that was spawned by the muxer itself, such as
+    /// a restarted child.
+    pub fn take_stdin(&mut self, pid: Pid) -> Option<ChildStdin> {
+        let child = self.children.get_mut(&pid)?;
+        match child.restarted_stdin.take() {
+            Some(stdin) => Some(stdin),
+            None => child.stdin.take()?.into_pipe().ok(),
+        }
+    }
+
+    /// Terminate a child and spawn it again once it exits, even if its
+    /// `RestartPolicy` would not restart it. A child still running after its
+    /// `Stop::grace`, or 10 seconds without one, is killed.
+    pub fn restart_child(&mut self, pid: Pid) -> io::Result<()> {
+        let child = self
+            .children
+            .get_mut(&pid)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ESRCH))?;
+        child.restart_requested = true;
+        let grace = child.spec.options.stop.grace.unwrap_or(RESTART_GRACE);
+        let state = child.handle.state.clone();
+        child.handle.terminate()?;
+        self.timers
+            .schedule(Instant::now() + grace, Timeout::Kill { child: state });
+        Ok(())
+    }
+
+    /// Allow or forbid restarting children according to their
+    /// `RestartPolicy`. Disabling restarts cancels any pending restart, which
+    /// is what you want before terminating children for shutdown.
+    pub fn set_restarts_enabled(&mut self, enabled: bool) {
+        self.restarts_enabled = enabled;
+        if !enabled {
+            self.timers
+                .retain(|timeout| !matches!(timeout, Timeout::Restart { .. }));
+        }
+    }
+
+    pub fn spawn(&mut self, cmd: Command) -> io::Result<ChildInfo> {
+        self.spawn_with(cmd, SpawnOptions::default())
+    }
+
+    pub fn spawn_with(&mut self, cmd: Command, options: SpawnOptions) -> io::Result<ChildInfo> {
+        let (handle, stdin) = self.spawn_spec(cmd, ChildSpec::new(options))?;
+        let child_info = ChildInfo {
+            pid: handle.pid(),
+            stdin,
+            handle,
+        };
+        Ok(child_info)
+    }
+
+    fn spawn_spec(
+        &mut self,
+        mut cmd: Command,
+        spec: ChildSpec,
+    ) -> io::Result<(ChildHandle, Option<ChildStdin>)> {
         let prog_path = PathBuf::from(cmd.get_program());
+        let args = cmd.get_args().map(|a| a.to_owned()).collect();
+        let cwd = cmd.get_current_dir().map(PathBuf::from);
+        let envs: Vec<_> = cmd
+            .get_envs()
+            .map(|(k, v)| (k.to_owned(), v.map(|v| v.to_owned())))
+            .collect();
+        if !spec.options.listeners.is_empty() {
+            cmd = activation::wrap(&cmd);
+        }
+        spec.apply(&mut cmd, &envs);
+        if let Some(pgroup) = spec.options.process_group {
+            cmd.process_group(pgroup);
+        }
+        let label: Rc<str> = match &spec.options.label {
+            Some(label) => label.as_str().into(),
+            None => prog_path.display().to_string().into(),
+        };
+        // Bound before spawning so that the child can notify as soon as it
+        // starts. This is set after recording the environment, as a restarted
+        // child gets a socket of its own.
+        let notify = match spec.options.notify {
+            true => {
+                let socket = NotifySocket::bind()?;
+                cmd.env("NOTIFY_SOCKET", socket.path());
+                if let Some(watchdog) = &spec.options.watchdog {
+                    cmd.env("WATCHDOG_USEC", watchdog.timeout.as_micros().to_string());
+                }
+                Some(socket)
+            }
+            false => None,
+        };
+        limits::apply_limits(&mut cmd, &spec.options)?;
+        let cgroup = match &spec.options.cgroup {
+            Some(cgroup) => Some(self.place_in_cgroup(&mut cmd, cgroup, &label)?),
+            None => None,
+        };
+        let oom_kills_at_spawn = cgroup.as_ref().map_or(0, |dir| dir.oom_kills());
+        if let Some(identity) = &spec.options.identity {
+            identity::apply_identity(&mut cmd, identity)?;
+        }
+        let reserved = activation::pass_listeners(&mut cmd, &spec.options.listeners)?;
+        // Last, so that nothing can fail between taking the terminal and
+        // spawning.
+        let foreground = spec.options.foreground && self.prepare_foreground(&mut cmd);
+        if !foreground {
+            self.restore_sigttou(&mut cmd);
+        }
 
-        let mut child = cmd.spawn()?;
+        let spawned = cmd.spawn();
+        drop(reserved);
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(error) => {
+                if foreground {
+                    self.abort_foreground();
+                }
+                return Err(error);
+            }
+        };
         let pid = Pid { inner: child.id() };
+        if foreground {
+            if let Err(error) = self.give_terminal(pid) {
+                let _ = child.kill();
+                let _ = child.wait();
+                self.release_terminal(pid);
+                return Err(error);
+            }
+        }
         let registry = self.poll.registry();
-        let prog_path = Rc::new(prog_path);
 
-        let child_info = ChildInfo {
+        let mut state = ChildState::new(
             pid,
-            stdin: child.stdin.take(),
-            prog_path: prog_path.clone(),
-            exit_status: Rc::new(Cell::new(None)),
+            prog_path,
+            label,
+            args,
+            cwd,
+            envs,
+            self.retention.tail_lines,
+        );
+        if let Some(crash_loop) = spec.options.restart.crash_loop {
+            state.stderr_tail = OutputTail::new(crash_loop.stderr_lines);
+        }
+        state.cgroup = cgroup;
+        state.oom_kills_at_spawn = oom_kills_at_spawn;
+        state.latest = spec.latest.clone();
+        let handle = ChildHandle {
+            state: Rc::new(state),
         };
-
+        *spec.latest.borrow_mut() = Rc::downgrade(&handle.state);
         if let Some(stdout) = child.stdout.take() {
-            let prog_path = prog_path.clone();
-            let mut stdout = ChildOut::from_pipe(stdout, pid, prog_path);
+            handle.state.output.stdout.opened();
+            let mut stdout = ChildOut::from_pipe(stdout, handle.state.clone());
             let entry = self.fds.vacant_entry();
             registry.register(&mut stdout, Token(entry.key()), Interest::READABLE)?;
             entry.insert(EventSource::ReadableChild(stdout));
         }
 
         if let Some(stderr) = child.stderr.take() {
-            let prog_path = prog_path.clone();
-            let mut stderr = ChildOut::from_pipe(stderr, pid, prog_path);
+            handle.state.output.stderr.opened();
+            let mut stderr = ChildOut::from_pipe(stderr, handle.state.clone());
             let entry = self.fds.vacant_entry();
             registry.register(&mut stderr, Token(entry.key()), Interest::READABLE)?;
             entry.insert(EventSource::ReadableChild(stderr));
         }
 
+        if let Some(socket) = notify {
+            let source = NotifySource::new(socket, handle.state.clone());
+            self.register(EventSource::Notify(source));
+        }
+
+        if let Some(check) = &spec.options.health {
+            handle.state.healthy.set(Some(true));
+            let first = Instant::now() + check.interval;
+            self.timers.schedule(first, Timeout::HealthCheck { pid });
+        }
+        if let Some(watchdog) = &spec.options.watchdog {
+            let first = Instant::now() + watchdog.timeout;
+            self.timers.schedule(first, Timeout::Watchdog { pid });
+        }
+        if let Some(limits) = &spec.options.resources {
+            let first = Instant::now() + limits.interval;
+            self.timers
+                .schedule(first, Timeout::SampleResources { pid });
+        }
+        if let Some(deadline) = spec.options.deadline {
+            let at = Instant::now() + deadline.runtime;
+            self.timers.schedule(at, Timeout::Deadline { pid });
+        }
+
+        // The muxer keeps the stdin of children that take forwarded input.
+        let (stdin, kept_stdin) = match spec.options.stdin_input {
+            true => (None, child.stdin.take().map(KeptStdin::new).transpose()?),
+            false => (child.stdin.take(), None),
+        };
         let muxer_child = MuxerChild {
-            child,
-            prog_path: prog_path.clone(),
-            exit_status: child_info.exit_status.clone(),
+            handle: handle.clone(),
+            spec,
+            stdin: kept_stdin,
+            restarted_stdin: None,
+            restart_requested: false,
+            health: HealthState::default(),
+            resources: ResourceState::default(),
         };
 
         self.children.insert(pid, muxer_child);
-        Ok(child_info)
+        Ok((handle, stdin))
+    }
+
+    /// Queue a restart of a reaped child if its policy asks for one.
+    fn schedule_restart(&mut self, child: MuxerChild) {
+        let MuxerChild {
+            handle,
+            mut spec,
+            restart_requested,
+            ..
+        } = child;
+        let state = handle.state;
+        let policy = spec.options.restart;
+        let Some(exit_status) = state.exit_status.get() else {
+            return;
+        };
+        if !self.restarts_enabled
+            || !(restart_requested || policy.wants_restart(exit_status.success()))
+        {
+            return;
+        }
+        let now = Instant::now();
+        if let Some(crash_loop) = policy.crash_loop.filter(|_| !exit_status.success()) {
+            if crash_loop.record(&mut spec.failures, now) {
+                state.crash_looping.set(true);
+                let stderr = state
+                    .stderr_tail
+                    .lines()
+                    .into_iter()
+                    .map(|l| l.line)
+                    .collect();
+                self.notices.push_back(Notice::CrashLoop {
+                    child: state,
+                    failures: spec.failures.len() as u32,
+                    stderr,
+                });
+                return;
+            }
+        }
+        if now.duration_since(state.started_at) >= policy.reset_after {
+            spec.attempt = 0;
+        }
+        if policy.max_retries.is_some_and(|max| spec.attempt >= max) {
+            return;
+        }
+        spec.attempt += 1;
+        let delay = policy.delay(spec.attempt);
+        self.notices.push_back(Notice::ChildRestarting {
+            child: state.clone(),
+            attempt: spec.attempt,
+            delay,
+        });
+        let restart = Timeout::Restart {
+            previous: state,
+            spec: Box::new(spec),
+        };
+        self.timers.schedule(now + delay, restart);
+    }
+
+    fn restart(&mut self, previous: Rc<ChildState>, spec: ChildSpec) {
+        let cmd = spec.command(&previous);
+        match self.spawn_spec(cmd, spec) {
+            Ok((handle, stdin)) => {
+                if let Some(child) = self.children.get_mut(&handle.pid()) {
+                    child.restarted_stdin = stdin;
+                }
+                self.notices.push_back(Notice::ChildRestarted {
+                    child: handle.state,
+                    previous: previous.pid,
+                });
+            }
+            Err(error) => {
+                self.notices.push_back(Notice::RestartFailed {
+                    child: previous,
+                    error,
+                });
+            }
+        }
+    }
+
+    /// Emit `Event::TimerFired` from `pump` once `delay` has passed.
+    pub fn set_timer(&mut self, delay: Duration) -> TimerId {
+        let at = Instant::now() + delay;
+        let key = self.timers.schedule(at, Timeout::User);
+        TimerId { key }
+    }
+
+    /// Cancel a timer that has not fired yet. Returns false if it already
+    /// fired or was cancelled.
+    pub fn cancel_timer(&mut self, id: TimerId) -> bool {
+        self.timers.cancel(id.key).is_some()
+    }
+
+    fn handle_timeout(&mut self, key: TimerKey, timeout: Timeout) {
+        match timeout {
+            Timeout::Restart { previous, spec } => self.restart(previous, *spec),
+            Timeout::HealthCheck { pid } => self.run_health_check(pid),
+            Timeout::Watchdog { pid } => self.check_watchdog(pid),
+            Timeout::Deadline { pid } => self.deadline_exceeded(pid),
+            Timeout::SampleResources { pid } => self.sample_resources(pid),
+            Timeout::Kill { child } => self.kill_overdue(&child),
+            Timeout::StopCommands => self.reap_stop_commands(),
+            Timeout::RemoveCgroups => self.remove_dead_cgroups(),
+            Timeout::User => self.notices.push_back(Notice::TimerFired {
+                id: TimerId { key },
+            }),
+        }
+    }
+
+    /// Drop the notify socket of a reaped child. A socket that is being
+    /// drained is dropped by `pump` instead, once it runs dry.
+    fn close_notify(&mut self, pid: Pid) {
+        let registry = self.poll.registry();
+        let mut closed = Vec::new();
+        self.fds.retain(|key, source| {
+            if !matches!(source, EventSource::Notify(n) if n.child.pid == pid) {
+                return true;
+            }
+            source.deregister(registry).unwrap();
+            closed.push(Token(key));
+            false
+        });
+        // the slab key may be reused, so forget any readiness for it
+        self.pending_events
+            .retain(|ev| !closed.contains(&ev.token()));
     }
 
     fn register(&mut self, mut evsrc: EventSource) {
@@ -174,11 +749,27 @@ impl Muxer {
         let mut state = mem::replace(&mut self.state, State::Awaiting);
         let (state, event) = loop {
             match state {
+                State::Awaiting if !self.notices.is_empty() => {
+                    let notice = self.notices.pop_front().expect("notices to be non-empty");
+                    let result = func(notice.event());
+                    if let Some(r) = result {
+                        break (State::Awaiting, r);
+                    }
+                }
                 State::Awaiting => match self.pending_events.pop() {
                     None => {
+                        let now = Instant::now();
+                        if let Some((key, timeout)) = self.timers.pop_expired(now) {
+                            self.handle_timeout(key, timeout);
+                            continue;
+                        }
+                        let poll_timeout = self
+                            .timers
+                            .next_deadline()
+                            .map(|deadline| deadline.saturating_duration_since(now));
                         // fill our events buffer
                         loop {
-                            match self.poll.poll(&mut self.events, None) {
+                            match self.poll.poll(&mut self.events, poll_timeout) {
                                 Ok(()) => break,
                                 Err(e) => match e.kind() {
                                     // if our poll is interrupted by a
@@ -203,7 +794,16 @@ impl Muxer {
                     // type before reregistering the underlying fd.
                     Some(ev) => match self.fds.remove(ev.token().0) {
                         EventSource::ChildTerminated(mut w) => {
-                            match w.handle_event(&mut self.children, &mut self.wait_buffer) {
+                            let mut changes = Vec::new();
+                            let instruction = w.handle_event(
+                                &mut self.children,
+                                &mut self.wait_buffer,
+                                &mut changes,
+                            );
+                            self.reap_health_probes();
+                            self.reap_stop_commands();
+                            self.job_changes(changes);
+                            match instruction {
                                 SourceInstruction::Reregister => {
                                     self.reregister(EventSource::ChildTerminated(w));
                                     state = State::DrainingChildTerminated;
@@ -216,6 +816,10 @@ impl Muxer {
                         EventSource::ReadableChild(child_out) => {
                             state = State::DrainingChildOut(child_out);
                         }
+                        EventSource::Notify(notify) => {
+                            state = State::DrainingNotify(notify);
+                        }
+                        EventSource::Stdin(stdin) => self.stdin_ready(stdin),
                         #[cfg(feature = "signals")]
                         EventSource::ReceivedSignal(signal_source) => {
                             state = State::DrainingSignals(signal_source);
@@ -224,11 +828,31 @@ impl Muxer {
                 },
                 State::DrainingChildTerminated => match self.wait_buffer.pop() {
                     None => state = State::Awaiting,
-                    Some((pid, prog_path, exit_status)) => {
+                    Some(muxer_child) => {
+                        let child = muxer_child.handle.state.clone();
+                        self.remember(muxer_child.handle.clone());
+                        self.close_notify(child.pid);
+                        self.release_terminal(child.pid);
+                        self.release_cgroup(&muxer_child);
+                        self.record_core_dump(&muxer_child);
+                        self.schedule_restart(muxer_child);
+                        #[cfg(feature = "signals")]
+                        self.shutdown_progress();
+                        let core_dump = child.core_dump.borrow().clone();
                         let event = Event::ChildTerminated {
-                            pid,
-                            prog_path: &prog_path,
-                            exit_status,
+                            pid: child.pid,
+                            label: &child.label,
+                            prog_path: &child.prog_path,
+                            exit_status: child
+                                .exit_status
+                                .get()
+                                .expect("reaped child to have an exit status"),
+                            runtime: child
+                                .exited_at
+                                .get()
+                                .unwrap_or_else(Instant::now)
+                                .duration_since(child.started_at),
+                            core_dump: core_dump.as_deref(),
                         };
                         match func(event) {
                             None => state = State::DrainingChildTerminated,
@@ -245,15 +869,17 @@ impl Muxer {
                         Ok(0) => {
                             // The fd was closed; we must deregister the fd and
                             // return to the awaiting state.
+                            child_out.child.output.get(child_out.tag).closed();
                             child_out
                                 .fd
                                 .get_mut()
                                 .deregister(self.poll.registry())
                                 .unwrap();
                             let event = Event::FdClosed {
-                                pid: child_out.pid,
+                                pid: child_out.child.pid,
+                                label: &child_out.child.label,
                                 tag: child_out.tag,
-                                prog_path: &child_out.prog_path,
+                                prog_path: &child_out.child.prog_path,
                             };
                             match func(event) {
                                 None => state = State::Awaiting,
@@ -263,10 +889,17 @@ impl Muxer {
                             }
                         }
                         Ok(_) => {
+                            child_out.child.output.get(child_out.tag).record_line(buf);
+                            child_out.child.tail.push(child_out.tag, buf);
+                            if child_out.tag == FdTag::Stderr {
+                                child_out.child.stderr_tail.push(child_out.tag, buf);
+                            }
+                            child_out.child.last_activity.set(Instant::now());
                             let event = Event::ChildWrote {
-                                pid: child_out.pid,
+                                pid: child_out.child.pid,
+                                label: &child_out.child.label,
                                 tag: child_out.tag,
-                                prog_path: &child_out.prog_path,
+                                prog_path: &child_out.child.prog_path,
                                 line: buf,
                             };
                             let ores = func(event);
@@ -290,8 +923,37 @@ impl Muxer {
                         Err(e) => panic!("Unexpected error when reading child output: {e}"),
                     }
                 }
+                State::DrainingNotify(mut notify) => match notify.next() {
+                    EventStream::Emit(()) => {
+                        let event = Event::ChildNotified {
+                            pid: notify.child.pid,
+                            label: &notify.child.label,
+                            fields: &notify.fields,
+                        };
+                        match func(event) {
+                            Some(r) => {
+                                break (State::DrainingNotify(notify), r);
+                            }
+                            None => state = State::DrainingNotify(notify),
+                        }
+                    }
+                    EventStream::Drained(source_instruction) => {
+                        let event_source = EventSource::Notify(notify);
+                        match source_instruction {
+                            SourceInstruction::Reregister => self.reregister(event_source),
+                            SourceInstruction::Deregister => self.deregister(event_source),
+                        }
+                        state = State::Awaiting;
+                    }
+                },
                 #[cfg(feature = "signals")]
                 State::DrainingSignals(mut signal_source) => match signal_source.next() {
+                    EventStream::Emit(Signal::Interrupt) if self.interrupt_shutdown() => {
+                        state = State::DrainingSignals(signal_source);
+                    }
+                    EventStream::Emit(signal) if self.forward_signal(signal) => {
+                        state = State::DrainingSignals(signal_source);
+                    }
                     EventStream::Emit(signal) => {
                         let event = Event::SignalReceived { signal };
                         match func(event) {
@@ -320,6 +982,8 @@ impl Muxer {
 enum EventSource {
     ReadableChild(ChildOut),
     ChildTerminated(ChildTerminationSource),
+    Notify(NotifySource),
+    Stdin(StdinSource),
     #[cfg(feature = "signals")]
     ReceivedSignal(SignalSource),
 }
@@ -334,6 +998,8 @@ impl Source for EventSource {
         match self {
             EventSource::ReadableChild(x) => x.register(registry, token, interests),
             EventSource::ChildTerminated(x) => x.register(registry, token, interests),
+            EventSource::Notify(x) => x.register(registry, token, interests),
+            EventSource::Stdin(x) => x.register(registry, token, interests),
             #[cfg(feature = "signals")]
             EventSource::ReceivedSignal(x) => x.register(registry, token, interests),
         }
@@ -348,6 +1014,8 @@ impl Source for EventSource {
         match self {
             EventSource::ReadableChild(x) => x.reregister(registry, token, interests),
             EventSource::ChildTerminated(x) => x.reregister(registry, token, interests),
+            EventSource::Notify(x) => x.reregister(registry, token, interests),
+            EventSource::Stdin(x) => x.reregister(registry, token, interests),
             #[cfg(feature = "signals")]
             EventSource::ReceivedSignal(x) => x.reregister(registry, token, interests),
         }
@@ -357,6 +1025,8 @@ impl Source for EventSource {
         match self {
             EventSource::ReadableChild(x) => x.deregister(registry),
             EventSource::ChildTerminated(x) => x.deregister(registry),
+            EventSource::Notify(x) => x.deregister(registry),
+            EventSource::Stdin(x) => x.deregister(registry),
             #[cfg(feature = "signals")]
             EventSource::ReceivedSignal(x) => x.deregister(registry),
         }
@@ -368,12 +1038,53 @@ enum State {
     Awaiting,
     DrainingChildOut(ChildOut),
     DrainingChildTerminated,
+    DrainingNotify(NotifySource),
     #[cfg(feature = "signals")]
     DrainingSignals(SignalSource),
 }
 
 pub struct MuxerChild {
-    child: Child,
-    prog_path: Rc<PathBuf>,
-    exit_status: Rc<Cell<Option<ExitStatus>>>,
+    handle: ChildHandle,
+    spec: ChildSpec,
+    /// The stdin of a child spawned with `SpawnOptions::stdin_input`, which
+    /// receives forwarded input.
+    stdin: Option<KeptStdin>,
+    /// The piped stdin of a restarted child without `stdin_input`, until it
+    /// is taken with `Muxer::take_stdin`.
+    restarted_stdin: Option<ChildStdin>,
+    /// Restart once the child exits, regardless of its policy.
+    restart_requested: bool,
+    health: HealthState,
+    resources: ResourceState,
+}
+
+/// Work scheduled for a point in time, serviced by `pump` between polls.
+#[derive(Debug)]
+enum Timeout {
+    Restart {
+        previous: Rc<ChildState>,
+        spec: Box<ChildSpec>,
+    },
+    User,
+    HealthCheck {
+        pid: Pid,
+    },
+    Watchdog {
+        pid: Pid,
+    },
+    Deadline {
+        pid: Pid,
+    },
+    SampleResources {
+        pid: Pid,
+    },
+    /// Kill a child that was asked to exit and has not. The child is kept
+    /// rather than its pid, which a restarted child may have reused.
+    Kill {
+        child: Rc<ChildState>,
+    },
+    /// Kill stop commands that have run too long.
+    StopCommands,
+    /// Remove killed cgroups that have emptied.
+    RemoveCgroups,
 }
@@ -0,0 +1,185 @@
+use std::{io, process::ExitStatus, rc::Rc, time::Duration};
+
+use crate::muxer::input::Focus;
+use crate::muxer::process::handle::ChildState;
+use crate::muxer::process::resources::Resource;
+#[cfg(feature = "signals")]
+use crate::muxer::shutdown::ShutdownStage;
+use crate::muxer::timer::TimerId;
+use crate::muxer::Event;
+use crate::{Pid, Signal};
+
+/// An event generated by the muxer itself rather than read from a source.
+/// Notices are queued and emitted from `pump` before polling again.
+#[derive(Debug)]
+pub(crate) enum Notice {
+    ChildRestarting {
+        child: Rc<ChildState>,
+        attempt: u32,
+        delay: Duration,
+    },
+    ChildRestarted {
+        child: Rc<ChildState>,
+        previous: Pid,
+    },
+    RestartFailed {
+        child: Rc<ChildState>,
+        error: io::Error,
+    },
+    CrashLoop {
+        child: Rc<ChildState>,
+        failures: u32,
+        stderr: Vec<String>,
+    },
+    TimerFired {
+        id: TimerId,
+    },
+    HealthChanged {
+        child: Rc<ChildState>,
+        healthy: bool,
+    },
+    WatchdogExpired {
+        child: Rc<ChildState>,
+        silent: Duration,
+    },
+    DeadlineExceeded {
+        child: Rc<ChildState>,
+        runtime: Duration,
+    },
+    ResourceSample {
+        child: Rc<ChildState>,
+        rss: u64,
+        cpu: f64,
+    },
+    ResourceLimitExceeded {
+        child: Rc<ChildState>,
+        resource: Resource,
+    },
+    OomKilled {
+        child: Rc<ChildState>,
+        kills: u64,
+    },
+    ChildStopped {
+        child: Rc<ChildState>,
+        signal: Signal,
+    },
+    ChildContinued {
+        child: Rc<ChildState>,
+    },
+    FocusChanged {
+        focus: Focus,
+    },
+    StdinUnrouted {
+        line: String,
+        child: Option<Rc<ChildState>>,
+    },
+    StopCommandFailed {
+        child: Rc<ChildState>,
+        exit_status: Option<ExitStatus>,
+    },
+    #[cfg(feature = "signals")]
+    Shutdown {
+        stage: ShutdownStage,
+    },
+    #[cfg(feature = "signals")]
+    SignalForwarded {
+        child: Rc<ChildState>,
+        signal: Signal,
+    },
+}
+
+impl Notice {
+    pub(crate) fn event(&self) -> Event<'_> {
+        match self {
+            Notice::ChildRestarting {
+                child,
+                attempt,
+                delay,
+            } => Event::ChildRestarting {
+                pid: child.pid,
+                label: &child.label,
+                attempt: *attempt,
+                delay: *delay,
+            },
+            Notice::ChildRestarted { child, previous } => Event::ChildRestarted {
+                pid: child.pid,
+                label: &child.label,
+                previous: *previous,
+            },
+            Notice::RestartFailed { child, error } => Event::RestartFailed {
+                pid: child.pid,
+                label: &child.label,
+                error,
+            },
+            Notice::CrashLoop {
+                child,
+                failures,
+                stderr,
+            } => Event::CrashLoop {
+                pid: child.pid,
+                label: &child.label,
+                failures: *failures,
+                stderr,
+            },
+            Notice::TimerFired { id } => Event::TimerFired { id: *id },
+            Notice::HealthChanged { child, healthy } => Event::HealthChanged {
+                pid: child.pid,
+                label: &child.label,
+                healthy: *healthy,
+            },
+            Notice::WatchdogExpired { child, silent } => Event::WatchdogExpired {
+                pid: child.pid,
+                label: &child.label,
+                silent: *silent,
+            },
+            Notice::DeadlineExceeded { child, runtime } => Event::DeadlineExceeded {
+                pid: child.pid,
+                label: &child.label,
+                runtime: *runtime,
+            },
+            Notice::ResourceSample { child, rss, cpu } => Event::ResourceSample {
+                pid: child.pid,
+                label: &child.label,
+                rss: *rss,
+                cpu: *cpu,
+            },
+            Notice::ResourceLimitExceeded { child, resource } => Event::ResourceLimitExceeded {
+                pid: child.pid,
+                label: &child.label,
+                resource: *resource,
+            },
+            Notice::OomKilled { child, kills } => Event::OomKilled {
+                pid: child.pid,
+                label: &child.label,
+                kills: *kills,
+            },
+            Notice::ChildStopped { child, signal } => Event::ChildStopped {
+                pid: child.pid,
+                label: &child.label,
+                signal: *signal,
+            },
+            Notice::ChildContinued { child } => Event::ChildContinued {
+                pid: child.pid,
+                label: &child.label,
+            },
+            Notice::FocusChanged { focus } => Event::FocusChanged { focus },
+            Notice::StdinUnrouted { line, child } => Event::StdinUnrouted {
+                line,
+                label: child.as_ref().map(|child| &*child.label),
+            },
+            Notice::StopCommandFailed { child, exit_status } => Event::StopCommandFailed {
+                pid: child.pid,
+                label: &child.label,
+                exit_status: *exit_status,
+            },
+            #[cfg(feature = "signals")]
+            Notice::Shutdown { stage } => Event::Shutdown { stage: *stage },
+            #[cfg(feature = "signals")]
+            Notice::SignalForwarded { child, signal } => Event::SignalForwarded {
+                pid: child.pid,
+                label: &child.label,
+                signal: *signal,
+            },
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::{
+    fs::File,
+    io,
+    net::{SocketAddr, TcpListener},
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+        unix::{net::UnixListener, process::CommandExt},
+    },
+    path::Path,
+    process::Command,
+    rc::Rc,
+};
+
+/// The first file descriptor used for passed listeners, as in
+/// `SD_LISTEN_FDS_START`.
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Run by `/bin/sh` in place of a child that is passed listeners, to set
+/// `LISTEN_PID` to its own pid before exec'ing the child's program.
+const WRAPPER: &str = r#"export LISTEN_PID=$$ && exec "$0" "$@""#;
+
+/// A listening socket bound by the muxer and passed to children using the
+/// `LISTEN_FDS` socket activation convention. Clones share the socket, so a
+/// restarted child inherits the same one and no connections are dropped.
+#[derive(Debug, Clone)]
+pub struct Listener {
+    name: String,
+    fd: Rc<OwnedFd>,
+}
+
+impl Listener {
+    /// Bind a TCP listener. `name` is passed to the child in
+    /// `LISTEN_FDNAMES`.
+    pub fn tcp(name: impl Into<String>, addr: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        Ok(Self::from_fd(name, listener.into()))
+    }
+
+    /// Bind a Unix stream listener at `path`.
+    pub fn unix(name: impl Into<String>, path: impl AsRef<Path>) -> io::Result<Self> {
+        let listener = UnixListener::bind(path)?;
+        Ok(Self::from_fd(name, listener.into()))
+    }
+
+    /// Pass an already listening socket.
+    pub fn from_fd(name: impl Into<String>, fd: OwnedFd) -> Self {
+        Self {
+            name: name.into(),
+            fd: Rc::new(fd),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl AsRawFd for Listener {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+/// Rebuild `cmd` to run through a shell that sets `LISTEN_PID`, which must
+/// hold the child's own pid and so cannot be set before fork, and then execs
+/// the program so that it keeps that pid. As on restart, only what `Command`
+/// exposes is carried over; stdio and environment clearing are applied to the
+/// wrapper from `SpawnOptions`.
+pub(crate) fn wrap(cmd: &Command) -> Command {
+    let mut wrapped = Command::new("/bin/sh");
+    wrapped
+        .arg("-c")
+        .arg(WRAPPER)
+        .arg(cmd.get_program())
+        .args(cmd.get_args());
+    if let Some(dir) = cmd.get_current_dir() {
+        wrapped.current_dir(dir);
+    }
+    for (key, value) in cmd.get_envs() {
+        match value {
+            Some(value) => wrapped.env(key, value),
+            None => wrapped.env_remove(key),
+        };
+    }
+    wrapped
+}
+
+/// Arrange for `listeners` to be passed to the child spawned by `cmd`, which
+/// must have been wrapped with `wrap`, as fds 3, 4, ... along with
+/// `LISTEN_FDS` and `LISTEN_FDNAMES`.
+///
+/// The free fds in that range are reserved until the returned fds are
+/// dropped after spawning, so that the pipes `Command` creates, including the
+/// one it reports exec errors through, are not placed where the listeners go.
+pub(crate) fn pass_listeners(
+    cmd: &mut Command,
+    listeners: &[Listener],
+) -> io::Result<Vec<OwnedFd>> {
+    if listeners.is_empty() {
+        return Ok(Vec::new());
+    }
+    let names: Vec<&str> = listeners.iter().map(|l| l.name()).collect();
+    cmd.env("LISTEN_FDS", listeners.len().to_string());
+    cmd.env("LISTEN_FDNAMES", names.join(":"));
+
+    let end = LISTEN_FDS_START + listeners.len() as RawFd;
+    let null = File::open("/dev/null")?;
+    let mut reserved = Vec::new();
+    for fd in LISTEN_FDS_START..end {
+        if unsafe { libc::fcntl(fd, libc::F_GETFD) } != -1 {
+            continue;
+        }
+        // `fd` is free, so it is the lowest one at or above itself.
+        let dup = unsafe { libc::fcntl(null.as_raw_fd(), libc::F_DUPFD_CLOEXEC, fd) };
+        if dup == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        reserved.push(unsafe { OwnedFd::from_raw_fd(dup) });
+    }
+
+    let mut fds: Vec<RawFd> = listeners.iter().map(|l| l.as_raw_fd()).collect();
+    // Only async-signal-safe calls are made after fork, and nothing is
+    // allocated.
+    unsafe {
+        cmd.pre_exec(move || {
+            // Move every listener out of the target range first, so that
+            // placing one cannot clobber another that has yet to be placed.
+            for fd in fds.iter_mut() {
+                let moved = libc::fcntl(*fd, libc::F_DUPFD_CLOEXEC, end);
+                if moved == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                *fd = moved;
+            }
+            // dup2 clears FD_CLOEXEC on the new descriptor.
+            for (target, fd) in (LISTEN_FDS_START..).zip(fds.iter()) {
+                if libc::dup2(*fd, target) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+    Ok(reserved)
+}
@@ -0,0 +1,247 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind},
+    os::{
+        fd::AsRawFd,
+        unix::{fs::OpenOptionsExt, process::CommandExt},
+    },
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::muxer::notice::Notice;
+use crate::muxer::{Muxer, MuxerChild, Timeout};
+
+/// Placement of a child in a cgroup v2 hierarchy. `parent` must be a
+/// delegated subtree the muxer can create cgroups in, with the controllers
+/// for any limits enabled in its `cgroup.subtree_control`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cgroup {
+    pub parent: PathBuf,
+    /// Share a cgroup named `group` with other children that use the same
+    /// name. Otherwise the child gets a cgroup of its own, which is killed
+    /// and removed once the child exits. The limits of a shared cgroup are
+    /// set by the child that creates it, and a child asking for different
+    /// ones fails to spawn while the group exists.
+    pub group: Option<String>,
+    /// `memory.max`, in bytes.
+    pub memory_max: Option<u64>,
+    /// `cpu.max`, in cores.
+    pub cpu_max: Option<f64>,
+    /// `pids.max`.
+    pub pids_max: Option<u64>,
+}
+
+impl Cgroup {
+    pub fn new(parent: impl Into<PathBuf>) -> Self {
+        Self {
+            parent: parent.into(),
+            group: None,
+            memory_max: None,
+            cpu_max: None,
+            pids_max: None,
+        }
+    }
+}
+
+/// The `cpu.max` period, in microseconds.
+const CPU_PERIOD: u64 = 100_000;
+
+/// How often a killed cgroup is checked for being empty.
+const REMOVE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How long a killed cgroup may take to empty before it is left behind.
+const REMOVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A cgroup created by the muxer. It is removed when dropped, if it is empty
+/// by then.
+#[derive(Debug)]
+pub(crate) struct CgroupDir {
+    pub(crate) path: PathBuf,
+    /// Whether children share the cgroup through `Cgroup::group`.
+    pub(crate) shared: bool,
+    /// The `Cgroup` the limits were applied from.
+    limits: Cgroup,
+}
+
+impl CgroupDir {
+    fn create(cgroup: &Cgroup, label: &str) -> io::Result<Self> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let (name, shared) = match &cgroup.group {
+            Some(group) => (group.clone(), true),
+            None => {
+                let n = NEXT.fetch_add(1, Ordering::Relaxed);
+                let label = label.replace(['/', ' '], "_");
+                let name = format!("{label}-{}-{n}", std::process::id());
+                (name, false)
+            }
+        };
+        let path = cgroup.parent.join(name);
+        match fs::create_dir(&path) {
+            Err(e) if e.kind() == ErrorKind::AlreadyExists && shared => (),
+            res => res?,
+        }
+        let dir = Self {
+            path,
+            shared,
+            limits: cgroup.clone(),
+        };
+        dir.apply_limits()?;
+        Ok(dir)
+    }
+
+    fn write(&self, file: &str, value: &str) -> io::Result<()> {
+        fs::write(self.path.join(file), value)
+    }
+
+    fn apply_limits(&self) -> io::Result<()> {
+        let cgroup = &self.limits;
+        if let Some(memory_max) = cgroup.memory_max {
+            self.write("memory.max", &memory_max.to_string())?;
+        }
+        if let Some(cpu_max) = cgroup.cpu_max {
+            let quota = (cpu_max * CPU_PERIOD as f64) as u64;
+            self.write("cpu.max", &format!("{quota} {CPU_PERIOD}"))?;
+        }
+        if let Some(pids_max) = cgroup.pids_max {
+            self.write("pids.max", &pids_max.to_string())?;
+        }
+        Ok(())
+    }
+
+    /// Whether the limits of `cgroup` are the ones this cgroup has.
+    fn same_limits(&self, cgroup: &Cgroup) -> bool {
+        let limits = &self.limits;
+        limits.memory_max == cgroup.memory_max
+            && limits.cpu_max == cgroup.cpu_max
+            && limits.pids_max == cgroup.pids_max
+    }
+
+    /// The `oom_kill` count of `memory.events`. 0 if the memory controller
+    /// is not enabled.
+    pub(crate) fn oom_kills(&self) -> u64 {
+        let events = fs::read_to_string(self.path.join("memory.events")).unwrap_or_default();
+        events
+            .lines()
+            .find_map(|line| line.strip_prefix("oom_kill "))
+            .and_then(|count| count.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// Kill every process in the cgroup with `cgroup.kill`.
+    pub(crate) fn kill(&self) -> io::Result<()> {
+        self.write("cgroup.kill", "1")
+    }
+
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for CgroupDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir(&self.path);
+    }
+}
+
+impl Muxer {
+    /// Create or reuse the cgroup for a child about to be spawned by `cmd`,
+    /// and have the child join it in `pre_exec`.
+    pub(crate) fn place_in_cgroup(
+        &mut self,
+        cmd: &mut Command,
+        cgroup: &Cgroup,
+        label: &str,
+    ) -> io::Result<Rc<CgroupDir>> {
+        let shared = cgroup
+            .group
+            .as_ref()
+            .and_then(|group| self.cgroups.get(&cgroup.parent.join(group)))
+            .and_then(|dir| dir.upgrade());
+        let dir = match shared {
+            Some(dir) if !dir.same_limits(cgroup) => {
+                return Err(io::Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "cgroup {} already exists with different limits",
+                        dir.path.display()
+                    ),
+                ));
+            }
+            Some(dir) => dir,
+            None => {
+                let dir = Rc::new(CgroupDir::create(cgroup, label)?);
+                if dir.shared {
+                    self.cgroups.insert(dir.path.clone(), Rc::downgrade(&dir));
+                }
+                dir
+            }
+        };
+        self.cgroups.retain(|_, dir| dir.strong_count() > 0);
+
+        let procs: File = OpenOptions::new()
+            .write(true)
+            .custom_flags(libc::O_CLOEXEC)
+            .open(dir.path.join("cgroup.procs"))?;
+        unsafe {
+            cmd.pre_exec(move || {
+                // Writing 0 moves the writing process.
+                let written = libc::write(procs.as_raw_fd(), b"0".as_ptr().cast(), 1);
+                if written == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                Ok(())
+            });
+        }
+        Ok(dir)
+    }
+
+    /// Report OOM kills in the cgroup of a reaped child and, if the cgroup
+    /// is its own, kill anything left in it and remove it once it is empty.
+    pub(crate) fn release_cgroup(&mut self, child: &MuxerChild) {
+        let state = &child.handle.state;
+        let Some(dir) = &state.cgroup else {
+            return;
+        };
+        let kills = dir.oom_kills().saturating_sub(state.oom_kills_at_spawn);
+        if kills > 0 {
+            self.notices.push_back(Notice::OomKilled {
+                child: state.clone(),
+                kills,
+            });
+        }
+        if !dir.shared && dir.kill().is_ok() {
+            // a check is already scheduled if others are waiting
+            let scheduled = !self.dead_cgroups.is_empty();
+            let give_up_at = Instant::now() + REMOVE_TIMEOUT;
+            self.dead_cgroups.push((dir.path.clone(), give_up_at));
+            if !scheduled {
+                self.remove_dead_cgroups();
+            }
+        }
+    }
+
+    /// Remove the killed cgroups that have emptied, and check the others
+    /// again later. `cgroup.kill` is asynchronous, so a cgroup is usually
+    /// still populated right after it.
+    pub(crate) fn remove_dead_cgroups(&mut self) {
+        let now = Instant::now();
+        self.dead_cgroups.retain(|(path, give_up_at)| {
+            let events = fs::read_to_string(path.join("cgroup.events")).unwrap_or_default();
+            let populated = events.lines().any(|line| line == "populated 1");
+            let removed = !populated
+                && match fs::remove_dir(path) {
+                    Err(e) => e.kind() == ErrorKind::NotFound,
+                    Ok(()) => true,
+                };
+            !removed && now < *give_up_at
+        });
+        if !self.dead_cgroups.is_empty() {
+            self.timers
+                .schedule(now + REMOVE_INTERVAL, Timeout::RemoveCgroups);
+        }
+    }
+}
@@ -0,0 +1,327 @@
+use std::{
+    env, fs,
+    os::unix::{ffi::OsStrExt, process::ExitStatusExt},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use crate::muxer::process::handle::ChildState;
+use crate::muxer::{Muxer, MuxerChild};
+
+/// Where to find a child's core dumps, and whether it may produce them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CoreDumps {
+    /// Raise the child's soft `RLIMIT_CORE` to its hard limit, so that it
+    /// dumps core even if the muxer's soft limit is 0.
+    pub enable: bool,
+    /// Look for core files in this directory, by the child's pid as a
+    /// separate number in their name, e.g. `core.1234`. Otherwise
+    /// `/proc/sys/kernel/core_pattern` is interpreted.
+    pub dir: Option<PathBuf>,
+}
+
+/// A piece of an expanded `core_pattern`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    /// A specifier whose value the muxer cannot know, such as the time of
+    /// the dump.
+    Any,
+}
+
+impl Muxer {
+    /// Locate the core file of a reaped child that dumped core, and delete
+    /// the oldest located core files beyond `Retention::core_dumps`.
+    pub(crate) fn record_core_dump(&mut self, child: &MuxerChild) {
+        let state = &child.handle.state;
+        if !state.exit_status.get().is_some_and(|s| s.core_dumped()) {
+            return;
+        }
+        let options = &child.spec.options.core_dumps;
+        let found = match &options.dir {
+            Some(dir) => find_in_dir(dir, state),
+            None => find_by_pattern(state),
+        };
+        let Some((path, modified)) = found else {
+            return;
+        };
+        state.core_dump.replace(Some(path.clone()));
+        // A pattern without the pid reuses the same file.
+        self.core_dumps.retain(|(p, _)| *p != path);
+        self.core_dumps.push_back((path, modified));
+        self.trim_core_dumps();
+    }
+
+    /// Delete the oldest recorded core files beyond the retention, unless
+    /// they have been replaced since they were recorded.
+    pub(crate) fn trim_core_dumps(&mut self) {
+        let Some(keep) = self.retention.core_dumps else {
+            return;
+        };
+        while self.core_dumps.len() > keep {
+            if let Some((path, modified)) = self.core_dumps.pop_front() {
+                if modified_at(&path) == Some(modified) {
+                    let _ = fs::remove_file(path);
+                }
+            }
+        }
+    }
+}
+
+/// The newest file in `dir` with the child's pid in its name, not delimited
+/// by other digits.
+fn find_in_dir(dir: &Path, state: &ChildState) -> Option<(PathBuf, SystemTime)> {
+    let pid = state.pid.inner.to_string();
+    let candidates = fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .file_name()
+                .as_bytes()
+                .split(|b| !b.is_ascii_digit())
+                .any(|number| number == pid.as_bytes())
+        })
+        .map(|entry| entry.path());
+    newest(candidates, state)
+}
+
+/// Interpret `core_pattern` for the child. Patterns that pipe the core to a
+/// helper such as systemd-coredump cannot be followed.
+fn find_by_pattern(state: &ChildState) -> Option<(PathBuf, SystemTime)> {
+    let pattern = fs::read_to_string("/proc/sys/kernel/core_pattern").ok()?;
+    let uses_pid =
+        fs::read_to_string("/proc/sys/kernel/core_uses_pid").is_ok_and(|value| value.trim() == "1");
+    let (dir, name) = locate(pattern.trim_end_matches('\n'), uses_pid, state)?;
+    let candidates = fs::read_dir(&dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| matches(entry.file_name().as_bytes(), &name))
+        .map(|entry| entry.path());
+    newest(candidates, state)
+}
+
+/// The directory that `pattern` puts the child's core file in, and the parts
+/// of its name.
+fn locate(pattern: &str, uses_pid: bool, state: &ChildState) -> Option<(PathBuf, Vec<Part>)> {
+    if pattern.starts_with('|') {
+        return None;
+    }
+    let mut parts = expand(pattern, state);
+    if uses_pid && !pattern.contains("%p") {
+        parts.push(Part::Literal(format!(".{}", state.pid.inner)));
+    }
+
+    // Only the file name may contain unknown parts.
+    let mut path = String::new();
+    let mut name = Vec::new();
+    for part in parts {
+        match part {
+            Part::Literal(text) => match text.rsplit_once('/') {
+                Some((dir, file)) => {
+                    if name.contains(&Part::Any) {
+                        return None;
+                    }
+                    for part in name.drain(..) {
+                        if let Part::Literal(text) = part {
+                            path.push_str(&text);
+                        }
+                    }
+                    path.push_str(dir);
+                    path.push('/');
+                    if !file.is_empty() {
+                        name.push(Part::Literal(file.to_owned()));
+                    }
+                }
+                None => name.push(Part::Literal(text)),
+            },
+            Part::Any => name.push(Part::Any),
+        }
+    }
+    let dir = match Path::new(&path) {
+        dir if dir.is_absolute() => dir.to_owned(),
+        dir => state
+            .cwd
+            .clone()
+            .or_else(|| env::current_dir().ok())?
+            .join(dir),
+    };
+    Some((dir, name))
+}
+
+/// Replace the `%` specifiers of `pattern` that are known for the child.
+fn expand(pattern: &str, state: &ChildState) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            literal.push(c);
+            continue;
+        }
+        let value = match chars.next() {
+            Some('%') => Some("%".to_owned()),
+            Some('p') | Some('P') | Some('i') | Some('I') => Some(state.pid.inner.to_string()),
+            Some('s') => state
+                .exit_status
+                .get()
+                .and_then(|s| s.signal())
+                .map(|signal| signal.to_string()),
+            // The command name, which the kernel truncates to 15 bytes.
+            Some('e') => state.prog_path.file_name().map(|name| {
+                let name = name.to_string_lossy();
+                name.char_indices()
+                    .take_while(|(i, c)| i + c.len_utf8() <= 15)
+                    .map(|(_, c)| c)
+                    .collect()
+            }),
+            _ => None,
+        };
+        match value {
+            Some(value) => literal.push_str(&value),
+            None => {
+                if !literal.is_empty() {
+                    parts.push(Part::Literal(std::mem::take(&mut literal)));
+                }
+                parts.push(Part::Any);
+            }
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(Part::Literal(literal));
+    }
+    parts
+}
+
+/// Whether `name` matches `parts`, where `Part::Any` matches any run of
+/// bytes.
+fn matches(name: &[u8], parts: &[Part]) -> bool {
+    match parts.split_first() {
+        None => name.is_empty(),
+        Some((Part::Literal(text), rest)) => name
+            .strip_prefix(text.as_bytes())
+            .is_some_and(|name| matches(name, rest)),
+        Some((Part::Any, rest)) => (0..=name.len()).any(|i| matches(&name[i..], rest)),
+    }
+}
+
+/// The newest of `paths` modified since the child started, so that cores
+/// left by earlier processes with the same pid are not picked up.
+fn newest(
+    paths: impl Iterator<Item = PathBuf>,
+    state: &ChildState,
+) -> Option<(PathBuf, SystemTime)> {
+    // File times come from a coarse clock that can lag by a few ticks.
+    let started = SystemTime::now() - state.started_at.elapsed() - Duration::from_millis(10);
+    paths
+        .filter_map(|path| Some((modified_at(&path)?, path)))
+        .filter(|(modified, _)| *modified >= started)
+        .max_by_key(|(modified, _)| *modified)
+        .map(|(modified, path)| (path, modified))
+}
+
+fn modified_at(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, process::ExitStatus, rc::Rc};
+
+    use super::*;
+    use crate::Pid;
+
+    fn child_state(program: &str) -> ChildState {
+        let state = ChildState::new(
+            Pid { inner: 1234 },
+            PathBuf::from(program),
+            Rc::from("test"),
+            Vec::new(),
+            Some(PathBuf::from("/work")),
+            Vec::new(),
+            0,
+        );
+        // SIGSEGV with a core dump.
+        state.reaped(ExitStatus::from_raw(libc::SIGSEGV | 0x80));
+        state
+    }
+
+    fn literal(text: &str) -> Part {
+        Part::Literal(text.to_owned())
+    }
+
+    #[test]
+    fn expand_known_specifiers() {
+        let state = child_state("/usr/bin/server");
+        assert_eq!(
+            expand("core.%e.%p.%s.%%", &state),
+            [literal("core.server.1234.11.%")]
+        );
+    }
+
+    #[test]
+    fn expand_unknown_specifiers() {
+        let state = child_state("/usr/bin/server");
+        assert_eq!(
+            expand("%t-core.%h.%p", &state),
+            [Part::Any, literal("-core."), Part::Any, literal(".1234")]
+        );
+    }
+
+    #[test]
+    fn expand_truncates_the_command_name() {
+        let state = child_state("/usr/bin/a-very-long-program-name");
+        assert_eq!(expand("%e", &state), [literal("a-very-long-pro")]);
+        let state = child_state("/usr/bin/ééééééééé");
+        assert_eq!(expand("%e", &state), [literal("ééééééé")]);
+    }
+
+    #[test]
+    fn matches_literals_and_wildcards() {
+        let parts = [literal("core."), Part::Any, literal(".1234")];
+        assert!(matches(b"core.server.1234", &parts));
+        assert!(matches(b"core..1234", &parts));
+        assert!(!matches(b"core.server.12345", &parts));
+        assert!(!matches(b"xcore.server.1234", &parts));
+        assert!(matches(b"", &[]));
+        assert!(!matches(b"core", &[]));
+        assert!(matches(b"anything", &[Part::Any]));
+    }
+
+    #[test]
+    fn locate_absolute_pattern() {
+        let state = child_state("/usr/bin/server");
+        let (dir, name) = locate("/var/crash/core.%e.%p", false, &state).unwrap();
+        assert_eq!(dir, Path::new("/var/crash"));
+        assert_eq!(name, [literal("core.server.1234")]);
+    }
+
+    #[test]
+    fn locate_relative_pattern_in_the_child_cwd() {
+        let state = child_state("/usr/bin/server");
+        let (dir, name) = locate("cores/%t.core", false, &state).unwrap();
+        assert_eq!(dir, Path::new("/work/cores"));
+        assert_eq!(name, [Part::Any, literal(".core")]);
+    }
+
+    #[test]
+    fn locate_appends_the_pid_with_core_uses_pid() {
+        let state = child_state("/usr/bin/server");
+        let (dir, name) = locate("core", true, &state).unwrap();
+        assert_eq!(dir, Path::new("/work"));
+        assert_eq!(name, [literal("core"), literal(".1234")]);
+        assert!(matches(b"core.1234", &name));
+        let (_, name) = locate("core.%p", true, &state).unwrap();
+        assert_eq!(name, [literal("core.1234")]);
+    }
+
+    #[test]
+    fn locate_rejects_pipes_and_unknown_directories() {
+        let state = child_state("/usr/bin/server");
+        assert_eq!(
+            locate("|/usr/lib/systemd/systemd-coredump %P", false, &state),
+            None
+        );
+        assert_eq!(locate("/var/crash/%h/core", false, &state), None);
+    }
+}
@@ -0,0 +1,60 @@
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::muxer::notice::Notice;
+use crate::muxer::process::handle::ChildState;
+use crate::muxer::{Muxer, Timeout};
+use crate::Pid;
+
+/// A maximum runtime for a child. Once it is exceeded the child is sent
+/// `SIGTERM`, and `SIGKILL` if it is still running `grace` later.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deadline {
+    pub runtime: Duration,
+    pub grace: Duration,
+}
+
+impl Deadline {
+    pub fn new(runtime: Duration) -> Self {
+        Self {
+            runtime,
+            grace: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Muxer {
+    /// Terminate `pid` for running past its deadline, and schedule the kill
+    /// in case it does not exit.
+    pub(crate) fn deadline_exceeded(&mut self, pid: Pid) {
+        let Some(child) = self.children.get(&pid) else {
+            return;
+        };
+        let Some(deadline) = child.spec.options.deadline else {
+            return;
+        };
+        let state = child.handle.state.clone();
+        let _ = child.handle.terminate();
+        let kill_at = Instant::now() + deadline.grace;
+        self.timers.schedule(
+            kill_at,
+            Timeout::Kill {
+                child: state.clone(),
+            },
+        );
+        self.notices.push_back(Notice::DeadlineExceeded {
+            runtime: state.started_at.elapsed(),
+            child: state,
+        });
+    }
+
+    /// Kill `state` if it has not been reaped since it was asked to exit.
+    pub(crate) fn kill_overdue(&mut self, state: &Rc<ChildState>) {
+        match self.children.get(&state.pid) {
+            Some(child) if Rc::ptr_eq(&child.handle.state, state) => {
+                let _ = child.handle.kill();
+            }
+            _ => {}
+        }
+    }
+}
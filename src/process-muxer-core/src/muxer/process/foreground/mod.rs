@@ -0,0 +1,268 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::OpenOptions,
+    io, mem,
+    os::{
+        fd::{AsRawFd, OwnedFd},
+        unix::{fs::OpenOptionsExt, process::CommandExt},
+    },
+    process::Command,
+    rc::Rc,
+};
+
+use crate::muxer::notice::Notice;
+use crate::muxer::process::handle::ChildState;
+use crate::muxer::Muxer;
+use crate::{Pid, Signal};
+
+/// The muxer's controlling terminal, while it hands it to foreground
+/// children. It is dropped once the last of them has been reaped.
+pub(crate) struct Terminal {
+    fd: OwnedFd,
+    /// The muxer's own process group, which gets the terminal back.
+    pgrp: libc::pid_t,
+    /// The terminal settings to restore when the muxer reclaims it.
+    termios: libc::termios,
+    /// The child that owns the terminal.
+    foreground: Option<Pid>,
+    /// Foreground children that have not been reaped yet.
+    jobs: BTreeSet<Pid>,
+    /// The `SIGTTOU` disposition to restore when the terminal is dropped.
+    sigttou: libc::sighandler_t,
+    /// The terminal settings of stopped foreground children, restored when
+    /// they are resumed.
+    stopped: BTreeMap<Pid, libc::termios>,
+}
+
+/// A change in a child's run state other than terminating, as reported by
+/// `waitpid`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum JobChange {
+    Stopped(Signal),
+    Continued,
+}
+
+impl Terminal {
+    /// Open the controlling terminal, if the muxer is in its foreground
+    /// process group.
+    fn open() -> Option<Self> {
+        let fd: OwnedFd = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_CLOEXEC | libc::O_NOCTTY)
+            .open("/dev/tty")
+            .ok()?
+            .into();
+        let pgrp = unsafe { libc::getpgrp() };
+        if unsafe { libc::tcgetpgrp(fd.as_raw_fd()) } != pgrp {
+            return None;
+        }
+        let mut termios: libc::termios = unsafe { mem::zeroed() };
+        if unsafe { libc::tcgetattr(fd.as_raw_fd(), &mut termios) } == -1 {
+            return None;
+        }
+        // The muxer must be able to take the terminal back while it is in
+        // the background.
+        let sigttou = unsafe { libc::signal(libc::SIGTTOU, libc::SIG_IGN) };
+        Some(Self {
+            fd,
+            pgrp,
+            termios,
+            foreground: None,
+            jobs: BTreeSet::new(),
+            sigttou,
+            stopped: BTreeMap::new(),
+        })
+    }
+
+    /// See `Muxer::restore_sigttou`.
+    pub(crate) fn restore_sigttou(&self, cmd: &mut Command) {
+        let sigttou = self.sigttou;
+        unsafe {
+            cmd.pre_exec(move || {
+                libc::signal(libc::SIGTTOU, sigttou);
+                Ok(())
+            });
+        }
+    }
+
+    fn give(&mut self, pid: Pid) -> io::Result<()> {
+        let fd = self.fd.as_raw_fd();
+        let pgrp = pid.inner as libc::pid_t;
+        // A child that has just been spawned takes the terminal itself
+        // before it execs, and may have exited since.
+        if unsafe { libc::tcsetpgrp(fd, pgrp) } == -1 {
+            let error = io::Error::last_os_error();
+            if unsafe { libc::tcgetpgrp(fd) } != pgrp {
+                return Err(error);
+            }
+        }
+        self.foreground = Some(pid);
+        self.jobs.insert(pid);
+        Ok(())
+    }
+
+    /// Take the terminal back from `pid` if it has it, keeping its settings
+    /// if it merely stopped.
+    fn reclaim(&mut self, pid: Pid, stopped: bool) {
+        if self.foreground != Some(pid) {
+            return;
+        }
+        let fd = self.fd.as_raw_fd();
+        if stopped {
+            let mut termios: libc::termios = unsafe { mem::zeroed() };
+            if unsafe { libc::tcgetattr(fd, &mut termios) } != -1 {
+                self.stopped.insert(pid, termios);
+            }
+        }
+        unsafe {
+            libc::tcsetpgrp(fd, self.pgrp);
+            libc::tcsetattr(fd, libc::TCSADRAIN, &self.termios);
+        }
+        self.foreground = None;
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        unsafe { libc::signal(libc::SIGTTOU, self.sigttou) };
+    }
+}
+
+impl Muxer {
+    /// Arrange for the child spawned by `cmd` to get the terminal, in a
+    /// process group of its own. Returns false, leaving `cmd` as is, if the
+    /// muxer does not have a terminal in the foreground to hand over.
+    pub(crate) fn prepare_foreground(&mut self, cmd: &mut Command) -> bool {
+        if self.terminal.is_none() {
+            self.terminal = Terminal::open();
+        }
+        let Some(terminal) = &self.terminal else {
+            return false;
+        };
+        let tty = terminal.fd.as_raw_fd();
+        cmd.process_group(0);
+        unsafe {
+            cmd.pre_exec(move || {
+                // The parent does the same after spawning, whichever runs
+                // first.
+                if libc::tcsetpgrp(tty, libc::getpid()) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+                for signum in [libc::SIGTSTP, libc::SIGTTIN, libc::SIGTTOU] {
+                    libc::signal(signum, libc::SIG_DFL);
+                }
+                Ok(())
+            });
+        }
+        true
+    }
+
+    /// Have the child spawned by `cmd` start with the `SIGTTOU` disposition
+    /// the muxer had before it took a terminal, rather than inheriting the
+    /// muxer's `SIG_IGN`.
+    pub(crate) fn restore_sigttou(&self, cmd: &mut Command) {
+        if let Some(terminal) = &self.terminal {
+            terminal.restore_sigttou(cmd);
+        }
+    }
+
+    /// Undo `prepare_foreground` for a child that failed to spawn. It may
+    /// have taken the terminal before its exec failed.
+    pub(crate) fn abort_foreground(&mut self) {
+        if let Some(terminal) = &self.terminal {
+            let owner = match terminal.foreground {
+                Some(pid) => pid.inner as libc::pid_t,
+                None => terminal.pgrp,
+            };
+            unsafe { libc::tcsetpgrp(terminal.fd.as_raw_fd(), owner) };
+            if terminal.jobs.is_empty() {
+                self.terminal = None;
+            }
+        }
+    }
+
+    /// Hand the terminal to a foreground child that has just been spawned.
+    pub(crate) fn give_terminal(&mut self, pid: Pid) -> io::Result<()> {
+        match &mut self.terminal {
+            Some(terminal) => terminal.give(pid),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether a foreground child owns the terminal.
+    pub(crate) fn terminal_in_use(&self) -> bool {
+        self.terminal
+            .as_ref()
+            .is_some_and(|t| t.foreground.is_some())
+    }
+
+    /// Take the terminal back from a child that has been reaped, and drop
+    /// it if that was the last foreground child.
+    pub(crate) fn release_terminal(&mut self, pid: Pid) {
+        if let Some(terminal) = &mut self.terminal {
+            terminal.reclaim(pid, false);
+            terminal.stopped.remove(&pid);
+            terminal.jobs.remove(&pid);
+            if terminal.jobs.is_empty() {
+                self.terminal = None;
+            }
+        }
+        self.resume_stdin();
+    }
+
+    /// Take the terminal back from whichever child has it.
+    #[cfg(feature = "signals")]
+    pub(crate) fn reclaim_terminal(&mut self) {
+        if let Some(terminal) = &mut self.terminal {
+            if let Some(pid) = terminal.foreground {
+                terminal.reclaim(pid, false);
+            }
+        }
+        self.resume_stdin();
+    }
+
+    /// Take the terminal back from children that stopped, and report stops
+    /// and continues.
+    pub(crate) fn job_changes(&mut self, changes: Vec<(Rc<ChildState>, JobChange)>) {
+        for (child, change) in changes {
+            let notice = match change {
+                JobChange::Stopped(signal) => {
+                    if let Some(terminal) = &mut self.terminal {
+                        terminal.reclaim(child.pid, true);
+                    }
+                    Notice::ChildStopped { child, signal }
+                }
+                JobChange::Continued => Notice::ChildContinued { child },
+            };
+            self.notices.push_back(notice);
+        }
+        self.resume_stdin();
+    }
+
+    /// Give the terminal back to a stopped foreground child and continue
+    /// it, as a shell's `fg` does.
+    pub fn resume_foreground(&mut self, pid: Pid) -> io::Result<()> {
+        let child = self
+            .children
+            .get(&pid)
+            .ok_or_else(|| io::Error::from_raw_os_error(libc::ESRCH))?;
+        if !child.spec.options.foreground {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "child was not spawned in the foreground",
+            ));
+        }
+        if let Some(terminal) = &mut self.terminal {
+            if let Some(termios) = terminal.stopped.remove(&pid) {
+                unsafe { libc::tcsetattr(terminal.fd.as_raw_fd(), libc::TCSADRAIN, &termios) };
+            }
+            terminal.give(pid)?;
+        }
+        let pgrp = -(pid.inner as libc::pid_t);
+        if unsafe { libc::kill(pgrp, Signal::Continue.as_raw()) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    cell::{Cell, RefCell},
+    ffi::OsString,
+    io,
+    path::{Path, PathBuf},
+    process::ExitStatus,
+    rc::{Rc, Weak},
+    time::Instant,
+};
+
+use crate::muxer::process::cgroup::CgroupDir;
+use crate::muxer::process::history::{OutputTail, TailLine};
+use crate::muxer::process::status::{ChildStatus, OutputCounters, RunState, Termination};
+use crate::muxer::{Event, Muxer};
+use crate::{Pid, Signal};
+
+/// The most recent child spawned from a `ChildSpec`, shared by every child
+/// spawned from it.
+pub(crate) type Latest = Rc<RefCell<Weak<ChildState>>>;
+
+/// A cloneable handle to a child process that was spawned with `Muxer`.
+///
+/// Signals are only delivered while the child has not been reaped, so a
+/// handle never signals an unrelated process that reused the pid.
+#[derive(Debug, Clone)]
+pub struct ChildHandle {
+    pub(crate) state: Rc<ChildState>,
+}
+
+/// State shared between the muxer and every handle to a child.
+#[derive(Debug)]
+pub(crate) struct ChildState {
+    pub(crate) pid: Pid,
+    pub(crate) prog_path: PathBuf,
+    pub(crate) label: Rc<str>,
+    pub(crate) args: Vec<OsString>,
+    pub(crate) cwd: Option<PathBuf>,
+    pub(crate) envs: Vec<(OsString, Option<OsString>)>,
+    pub(crate) started_at: Instant,
+    pub(crate) exited_at: Cell<Option<Instant>>,
+    pub(crate) exit_status: Cell<Option<ExitStatus>>,
+    pub(crate) stopped: Cell<bool>,
+    pub(crate) crash_looping: Cell<bool>,
+    /// `None` if the child has no health check.
+    pub(crate) healthy: Cell<Option<bool>>,
+    /// Whether the child sent `READY=1` to its notify socket.
+    pub(crate) ready: Cell<bool>,
+    pub(crate) notify_status: RefCell<Option<String>>,
+    /// When the child last wrote a line or sent `WATCHDOG=1`.
+    pub(crate) last_activity: Cell<Instant>,
+    pub(crate) cgroup: Option<Rc<CgroupDir>>,
+    /// The cgroup's OOM kill count before the child was spawned.
+    pub(crate) oom_kills_at_spawn: u64,
+    pub(crate) core_dump: RefCell<Option<PathBuf>>,
+    pub(crate) output: OutputCounters,
+    pub(crate) tail: OutputTail,
+    /// The last stderr lines, kept apart from `tail` for crash-loop reports
+    /// so that stdout cannot push them out.
+    pub(crate) stderr_tail: OutputTail,
+    pub(crate) latest: Latest,
+}
+
+impl ChildState {
+    pub(crate) fn new(
+        pid: Pid,
+        prog_path: PathBuf,
+        label: Rc<str>,
+        args: Vec<OsString>,
+        cwd: Option<PathBuf>,
+        envs: Vec<(OsString, Option<OsString>)>,
+        tail_lines: usize,
+    ) -> Self {
+        let started_at = Instant::now();
+        Self {
+            pid,
+            prog_path,
+            label,
+            args,
+            cwd,
+            envs,
+            started_at,
+            exited_at: Cell::new(None),
+            exit_status: Cell::new(None),
+            stopped: Cell::new(false),
+            crash_looping: Cell::new(false),
+            healthy: Cell::new(None),
+            ready: Cell::new(false),
+            notify_status: RefCell::new(None),
+            last_activity: Cell::new(started_at),
+            cgroup: None,
+            oom_kills_at_spawn: 0,
+            core_dump: RefCell::new(None),
+            output: OutputCounters::default(),
+            tail: OutputTail::new(tail_lines),
+            stderr_tail: OutputTail::new(0),
+            latest: Latest::default(),
+        }
+    }
+
+    /// Record the exit status of a child that has just been reaped.
+    pub(crate) fn reaped(&self, exit_status: ExitStatus) {
+        self.exit_status.set(Some(exit_status));
+        self.exited_at.set(Some(Instant::now()));
+        self.stopped.set(false);
+    }
+
+    pub(crate) fn status(&self) -> ChildStatus {
+        let exit_status = self.exit_status.get();
+        let state = match (exit_status, self.stopped.get()) {
+            (Some(_), _) => RunState::Exited,
+            (None, true) => RunState::Stopped,
+            (None, false) => RunState::Running,
+        };
+        let end = self.exited_at.get().unwrap_or_else(Instant::now);
+        ChildStatus {
+            pid: self.pid,
+            label: self.label.to_string(),
+            state,
+            uptime: end.duration_since(self.started_at),
+            exit_status,
+            crash_looping: self.crash_looping.get(),
+            healthy: self.healthy.get(),
+            ready: self.ready.get(),
+            notify_status: self.notify_status.borrow().clone(),
+            stdout: self.output.stdout.snapshot(),
+            stderr: self.output.stderr.snapshot(),
+        }
+    }
+}
+
+impl ChildHandle {
+    pub fn pid(&self) -> Pid {
+        self.state.pid
+    }
+
+    pub fn program(&self) -> &Path {
+        &self.state.prog_path
+    }
+
+    /// The label given at spawn time, or the program path if there was none.
+    pub fn label(&self) -> &str {
+        &self.state.label
+    }
+
+    pub fn args(&self) -> &[OsString] {
+        &self.state.args
+    }
+
+    /// The working directory set on the `Command`, if it was changed.
+    pub fn current_dir(&self) -> Option<&Path> {
+        self.state.cwd.as_deref()
+    }
+
+    /// Environment overrides set on the `Command`. A value of `None` means the
+    /// variable was removed.
+    pub fn envs(&self) -> &[(OsString, Option<OsString>)] {
+        &self.state.envs
+    }
+
+    pub fn started_at(&self) -> Instant {
+        self.state.started_at
+    }
+
+    /// When the child was reaped, if it has been.
+    pub fn exited_at(&self) -> Option<Instant> {
+        self.state.exited_at.get()
+    }
+
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.state.exit_status.get()
+    }
+
+    /// How the child terminated, once it has been reaped.
+    pub fn termination(&self) -> Option<Termination> {
+        self.exit_status().map(Termination::from)
+    }
+
+    /// The core file the child dumped, if it did and it could be found.
+    pub fn core_dump(&self) -> Option<PathBuf> {
+        self.state.core_dump.borrow().clone()
+    }
+
+    /// The most recent lines of output, oldest first, up to the muxer's
+    /// `Retention::tail_lines`.
+    pub fn output_tail(&self) -> Vec<TailLine> {
+        self.state.tail.lines()
+    }
+
+    /// A snapshot of the child's run state and output statistics.
+    pub fn status(&self) -> ChildStatus {
+        self.state.status()
+    }
+
+    /// Whether the child has sent `READY=1` over `NOTIFY_SOCKET`. Only
+    /// children spawned with `SpawnOptions::notify` can do so.
+    pub fn is_ready(&self) -> bool {
+        self.state.ready.get()
+    }
+
+    /// The latest `STATUS=` text the child sent over `NOTIFY_SOCKET`.
+    pub fn notify_status(&self) -> Option<String> {
+        self.state.notify_status.borrow().clone()
+    }
+
+    /// Returns true until the muxer has reaped the child.
+    pub fn is_running(&self) -> bool {
+        self.exit_status().is_none()
+    }
+
+    /// The child that replaced this one through its `RestartPolicy`, after
+    /// any number of restarts, or this child if it has not been restarted.
+    pub fn latest(&self) -> ChildHandle {
+        match self.state.latest.borrow().upgrade() {
+            Some(state) => ChildHandle { state },
+            None => self.clone(),
+        }
+    }
+
+    /// Send `signal` to the child. Fails with `ESRCH` once the child has been
+    /// reaped.
+    pub fn signal(&self, signal: Signal) -> io::Result<()> {
+        if !self.is_running() {
+            return Err(io::Error::from_raw_os_error(libc::ESRCH));
+        }
+        // The pid cannot have been reused: the muxer is the only one that
+        // reaps its children, and it records the exit status when it does.
+        let res = unsafe { libc::kill(self.state.pid.inner as i32, signal.as_raw()) };
+        if res == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Send SIGTERM to the child.
+    pub fn terminate(&self) -> io::Result<()> {
+        self.signal(Signal::Terminate)
+    }
+
+    /// Send SIGKILL to the child. A child in a cgroup of its own is killed
+    /// along with all of its descendants, through `cgroup.kill`.
+    pub fn kill(&self) -> io::Result<()> {
+        match &self.state.cgroup {
+            Some(dir) if !dir.shared && self.is_running() => dir.kill(),
+            _ => self.signal(Signal::Kill),
+        }
+    }
+
+    /// The cgroup the child was placed in with `SpawnOptions::cgroup`.
+    pub fn cgroup(&self) -> Option<&Path> {
+        self.state.cgroup.as_deref().map(CgroupDir::path)
+    }
+
+    /// Kill every process in the child's cgroup, including other children
+    /// sharing it and descendants left behind after the child exited.
+    pub fn kill_cgroup(&self) -> io::Result<()> {
+        match &self.state.cgroup {
+            Some(dir) => dir.kill(),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "child has no cgroup",
+            )),
+        }
+    }
+
+    /// Pump `muxer` until the child terminates. Events for other children are
+    /// discarded.
+    pub fn wait_with(&self, muxer: &mut Muxer) -> ExitStatus {
+        if let Some(exit_status) = self.exit_status() {
+            return exit_status;
+        }
+        muxer.pump(|ev| match ev {
+            Event::ChildTerminated {
+                pid, exit_status, ..
+            } if pid == self.state.pid => Some(exit_status),
+            _ => None,
+        })
+    }
+}
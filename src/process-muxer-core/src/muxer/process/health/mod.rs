@@ -0,0 +1,151 @@
+use std::{
+    ffi::OsString,
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use crate::muxer::notice::Notice;
+use crate::muxer::{Muxer, Timeout};
+use crate::Pid;
+
+/// A command run periodically to check that a child is still healthy, e.g.
+/// `pg_isready`. A zero exit status counts as a pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HealthCheck {
+    pub program: OsString,
+    pub args: Vec<OsString>,
+    /// How often the command runs. A command still running when the next
+    /// check is due is killed and counts as a failure.
+    pub interval: Duration,
+    /// The number of consecutive failures that mark the child as unhealthy.
+    pub failures: u32,
+    /// Restart the child when it becomes unhealthy, regardless of its
+    /// `RestartPolicy`.
+    pub restart: bool,
+}
+
+impl HealthCheck {
+    pub fn new<I, S>(program: impl Into<OsString>, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        Self {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+            interval: Duration::from_secs(10),
+            failures: 3,
+            restart: false,
+        }
+    }
+}
+
+/// Health-check bookkeeping for one child.
+#[derive(Debug, Default)]
+pub(crate) struct HealthState {
+    failures: u32,
+    /// The health-check command currently running for the child.
+    probe: Option<Pid>,
+}
+
+/// A running health-check command and the child it checks.
+#[derive(Debug)]
+pub(crate) struct HealthProbe {
+    target: Pid,
+    child: Child,
+}
+
+impl Muxer {
+    /// Run the health check of `pid` and schedule the next one.
+    pub(crate) fn run_health_check(&mut self, pid: Pid) {
+        let Some(child) = self.children.get_mut(&pid) else {
+            return;
+        };
+        let Some(check) = child.spec.options.health.clone() else {
+            return;
+        };
+        let overdue = child.health.probe.take();
+        if let Some(mut probe) = overdue.and_then(|p| self.health_probes.remove(&p)) {
+            let _ = probe.child.kill();
+            let _ = probe.child.wait();
+            self.health_result(pid, false);
+        }
+
+        let mut cmd = Command::new(&check.program);
+        cmd.args(&check.args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        self.restore_sigttou(&mut cmd);
+        let spawned = cmd.spawn();
+        match spawned {
+            Ok(probe) => {
+                let probe_pid = Pid { inner: probe.id() };
+                if let Some(child) = self.children.get_mut(&pid) {
+                    child.health.probe = Some(probe_pid);
+                }
+                let probe = HealthProbe {
+                    target: pid,
+                    child: probe,
+                };
+                self.health_probes.insert(probe_pid, probe);
+            }
+            Err(_) => self.health_result(pid, false),
+        }
+
+        let next = Instant::now() + check.interval;
+        self.timers.schedule(next, Timeout::HealthCheck { pid });
+    }
+
+    /// Collect the results of health-check commands that have exited.
+    pub(crate) fn reap_health_probes(&mut self) {
+        let mut finished = Vec::new();
+        for (probe_pid, probe) in self.health_probes.iter_mut() {
+            if let Ok(Some(exit_status)) = probe.child.try_wait() {
+                finished.push((*probe_pid, probe.target, exit_status.success()));
+            }
+        }
+        for (probe_pid, target, success) in finished {
+            self.health_probes.remove(&probe_pid);
+            if let Some(child) = self.children.get_mut(&target) {
+                if child.health.probe == Some(probe_pid) {
+                    child.health.probe = None;
+                }
+            }
+            self.health_result(target, success);
+        }
+    }
+
+    fn health_result(&mut self, pid: Pid, success: bool) {
+        let Some(child) = self.children.get_mut(&pid) else {
+            return;
+        };
+        let Some(check) = &child.spec.options.health else {
+            return;
+        };
+        let state = &child.handle.state;
+        let was_healthy = state.healthy.get() == Some(true);
+        let healthy = match success {
+            true => {
+                child.health.failures = 0;
+                true
+            }
+            false => {
+                child.health.failures += 1;
+                was_healthy && child.health.failures < check.failures
+            }
+        };
+        if healthy == was_healthy {
+            return;
+        }
+        state.healthy.set(Some(healthy));
+        let restart = !healthy && check.restart;
+        self.notices.push_back(Notice::HealthChanged {
+            child: state.clone(),
+            healthy,
+        });
+        if restart {
+            let _ = self.restart_child(pid);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::{cell::RefCell, collections::VecDeque};
+
+use crate::FdTag;
+
+/// How much the muxer remembers about children after they exit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Retention {
+    /// The number of exited children kept for `Muxer::history`. The oldest
+    /// record is dropped first.
+    pub children: usize,
+    /// The number of output lines kept per child, across both streams.
+    pub tail_lines: usize,
+    /// The number of core files located for children that are kept on disk.
+    /// The oldest file is deleted first. `None` keeps them all.
+    pub core_dumps: Option<usize>,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Self {
+            children: 32,
+            tail_lines: 20,
+            core_dumps: None,
+        }
+    }
+}
+
+/// A line of output remembered from a child.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TailLine {
+    pub tag: FdTag,
+    pub line: String,
+}
+
+/// A bounded buffer of a child's most recent output lines.
+#[derive(Debug)]
+pub(crate) struct OutputTail {
+    capacity: usize,
+    lines: RefCell<VecDeque<TailLine>>,
+}
+
+impl OutputTail {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            lines: RefCell::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    pub(crate) fn push(&self, tag: FdTag, line: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        let line = line.strip_suffix('\n').unwrap_or(line);
+        let mut lines = self.lines.borrow_mut();
+        // reuse the allocation of the line we are evicting
+        let mut entry = match lines.len() < self.capacity {
+            true => TailLine {
+                tag,
+                line: String::new(),
+            },
+            false => lines.pop_front().expect("a full tail to be non-empty"),
+        };
+        entry.tag = tag;
+        entry.line.clear();
+        entry.line.push_str(line);
+        lines.push_back(entry);
+    }
+
+    pub(crate) fn lines(&self) -> Vec<TailLine> {
+        self.lines.borrow().iter().cloned().collect()
+    }
+}
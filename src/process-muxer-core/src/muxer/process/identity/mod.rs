@@ -0,0 +1,238 @@
+use std::{
+    ffi::{CStr, CString},
+    io::{self, ErrorKind},
+    mem,
+    os::unix::process::CommandExt,
+    process::Command,
+    ptr,
+};
+
+/// The user, groups, umask and capabilities a child runs with. Changing
+/// the user or groups requires the muxer to run as root.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Identity {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+    /// Supplementary groups. When running as root and changing the uid or
+    /// gid, they default to none rather than root's.
+    pub groups: Option<Vec<u32>>,
+    pub umask: Option<u32>,
+    /// Drop every capability, including from the bounding set so that the
+    /// child cannot regain any by executing a privileged binary. Only
+    /// supported on Linux.
+    pub drop_capabilities: bool,
+}
+
+impl Identity {
+    /// The uid, primary group and supplementary groups of the user `name`,
+    /// from the user database.
+    pub fn user(name: &str) -> io::Result<Self> {
+        let c_name = CString::new(name).map_err(|e| io::Error::new(ErrorKind::InvalidInput, e))?;
+        let mut passwd: libc::passwd = unsafe { mem::zeroed() };
+        let mut found = ptr::null_mut();
+        let mut buf = vec![0 as libc::c_char; 1024];
+        loop {
+            let res = unsafe {
+                libc::getpwnam_r(
+                    c_name.as_ptr(),
+                    &mut passwd,
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut found,
+                )
+            };
+            match res {
+                0 => break,
+                libc::ERANGE => buf.resize(buf.len() * 2, 0),
+                err => return Err(io::Error::from_raw_os_error(err)),
+            }
+        }
+        if found.is_null() {
+            return Err(io::Error::new(
+                ErrorKind::NotFound,
+                format!("no such user: {name}"),
+            ));
+        }
+        let uid = passwd.pw_uid;
+        let gid = passwd.pw_gid;
+
+        let mut groups: Vec<libc::gid_t> = vec![0; 32];
+        loop {
+            let mut count = groups.len() as libc::c_int;
+            let res = unsafe {
+                // Groups are `c_int` rather than `gid_t` on some platforms.
+                libc::getgrouplist(
+                    CStr::from_ptr(passwd.pw_name).as_ptr(),
+                    gid as _,
+                    groups.as_mut_ptr().cast(),
+                    &mut count,
+                )
+            };
+            if res != -1 {
+                groups.truncate(count as usize);
+                break;
+            }
+            groups.resize((count as usize).max(groups.len() * 2), 0);
+        }
+        Ok(Self {
+            uid: Some(uid),
+            gid: Some(gid),
+            groups: Some(groups),
+            ..Self::default()
+        })
+    }
+}
+
+/// Check `identity` against the muxer's own privileges, and apply it to the
+/// child in `pre_exec`. This must come after any `pre_exec` that needs the
+/// muxer's privileges.
+pub(crate) fn apply_identity(cmd: &mut Command, identity: &Identity) -> io::Result<()> {
+    let euid = unsafe { libc::geteuid() };
+    let egid = unsafe { libc::getegid() };
+    let denied = |message: &str| io::Error::new(ErrorKind::PermissionDenied, message);
+    if euid != 0 {
+        if identity.uid.is_some_and(|uid| uid != euid) {
+            return Err(denied("changing the uid of a child requires root"));
+        }
+        if identity.gid.is_some_and(|gid| gid != egid) || identity.groups.is_some() {
+            return Err(denied("changing the groups of a child requires root"));
+        }
+        if identity.drop_capabilities {
+            return Err(denied("dropping capabilities requires root"));
+        }
+    }
+    if identity.drop_capabilities && !cfg!(target_os = "linux") {
+        return Err(io::Error::new(
+            ErrorKind::Unsupported,
+            "dropping capabilities is only supported on Linux",
+        ));
+    }
+    if identity.umask.is_some_and(|umask| umask > 0o777) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "umask is above 0o777",
+        ));
+    }
+
+    let changes_ids = identity.uid.is_some() || identity.gid.is_some();
+    let groups: Option<Vec<libc::gid_t>> = match &identity.groups {
+        Some(groups) => Some(groups.clone()),
+        None if euid == 0 && changes_ids => Some(Vec::new()),
+        None => None,
+    };
+    let identity = identity.clone();
+    unsafe {
+        cmd.pre_exec(move || {
+            let check = |res: libc::c_int| match res {
+                -1 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            };
+            if let Some(umask) = identity.umask {
+                libc::umask(umask as libc::mode_t);
+            }
+            if let Some(groups) = &groups {
+                check(libc::setgroups(groups.len() as _, groups.as_ptr()))?;
+            }
+            if let Some(gid) = identity.gid {
+                check(libc::setgid(gid))?;
+            }
+            if identity.drop_capabilities {
+                // Dropping from the bounding set needs CAP_SETPCAP, so this
+                // comes before giving up root.
+                capabilities::drop_bounding_set()?;
+            }
+            if let Some(uid) = identity.uid {
+                check(libc::setuid(uid))?;
+            }
+            if identity.drop_capabilities {
+                capabilities::clear()?;
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+/// Linux capabilities, dropped in `pre_exec`.
+#[cfg(target_os = "linux")]
+mod capabilities {
+    use std::io;
+
+    /// The version of `capset` taking two 32-bit words per set.
+    const CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+    #[repr(C)]
+    struct CapHeader {
+        version: u32,
+        pid: libc::c_int,
+    }
+
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct CapData {
+        effective: u32,
+        permitted: u32,
+        inheritable: u32,
+    }
+
+    fn check(res: libc::c_int) -> io::Result<()> {
+        match res {
+            -1 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Empty the bounding and ambient sets.
+    pub(super) fn drop_bounding_set() -> io::Result<()> {
+        let mut cap = 0;
+        while unsafe { libc::prctl(libc::PR_CAPBSET_READ, cap, 0, 0, 0) } >= 0 {
+            check(unsafe { libc::prctl(libc::PR_CAPBSET_DROP, cap, 0, 0, 0) })?;
+            cap += 1;
+        }
+        check(unsafe {
+            libc::prctl(
+                libc::PR_CAP_AMBIENT,
+                libc::PR_CAP_AMBIENT_CLEAR_ALL,
+                0,
+                0,
+                0,
+            )
+        })
+    }
+
+    /// Empty the effective, permitted and inheritable sets.
+    pub(super) fn clear() -> io::Result<()> {
+        let header = CapHeader {
+            version: CAPABILITY_VERSION_3,
+            pid: 0,
+        };
+        let data = [CapData {
+            effective: 0,
+            permitted: 0,
+            inheritable: 0,
+        }; 2];
+        check(unsafe { libc::syscall(libc::SYS_capset, &header, data.as_ptr()) } as libc::c_int)
+    }
+}
+
+/// Capabilities are Linux-specific; `apply_identity` rejects dropping them
+/// elsewhere.
+#[cfg(not(target_os = "linux"))]
+mod capabilities {
+    use std::io;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "dropping capabilities is only supported on Linux",
+        )
+    }
+
+    pub(super) fn drop_bounding_set() -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub(super) fn clear() -> io::Result<()> {
+        Err(unsupported())
+    }
+}
@@ -0,0 +1,155 @@
+use std::{io, mem, os::unix::process::CommandExt, process::Command};
+
+use crate::SpawnOptions;
+
+/// A resource limit that can be set for a child with `SpawnOptions::rlimits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rlimit {
+    /// `RLIMIT_NOFILE`, the number of open file descriptors.
+    NoFile,
+    /// `RLIMIT_AS`, the size of the address space in bytes.
+    AddressSpace,
+    /// `RLIMIT_CORE`, the size of core dumps in bytes.
+    Core,
+    /// `RLIMIT_CPU`, CPU time in seconds.
+    Cpu,
+}
+
+/// Soft and hard values of an `Rlimit`. `None` is unlimited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limit {
+    pub soft: Option<u64>,
+    pub hard: Option<u64>,
+}
+
+impl Limit {
+    /// Set both the soft and hard limit to `value`.
+    pub fn new(value: u64) -> Self {
+        Self {
+            soft: Some(value),
+            hard: Some(value),
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self {
+            soft: None,
+            hard: None,
+        }
+    }
+
+    fn as_raw(&self) -> libc::rlimit {
+        let raw = |value: Option<u64>| value.map_or(libc::RLIM_INFINITY, |v| v as libc::rlim_t);
+        libc::rlimit {
+            rlim_cur: raw(self.soft),
+            rlim_max: raw(self.hard),
+        }
+    }
+}
+
+/// Check the rlimits, nice level and CPU set in `options`, and apply them to
+/// the child in `pre_exec`.
+pub(crate) fn apply_limits(cmd: &mut Command, options: &SpawnOptions) -> io::Result<()> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let enable_cores =
+        options.core_dumps.enable && !options.rlimits.iter().any(|(r, _)| *r == Rlimit::Core);
+    if options.rlimits.is_empty()
+        && options.nice.is_none()
+        && options.cpus.is_none()
+        && !enable_cores
+    {
+        return Ok(());
+    }
+    let mut rlimits = Vec::with_capacity(options.rlimits.len() + 1);
+    if enable_cores {
+        let mut core: libc::rlimit = unsafe { mem::zeroed() };
+        if unsafe { libc::getrlimit(libc::RLIMIT_CORE, &mut core) } == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        core.rlim_cur = core.rlim_max;
+        rlimits.push((Rlimit::Core, core));
+    }
+    for (resource, limit) in options.rlimits.iter() {
+        let raw = limit.as_raw();
+        if raw.rlim_cur > raw.rlim_max {
+            return Err(invalid("soft rlimit is above the hard limit"));
+        }
+        rlimits.push((*resource, raw));
+    }
+    let nice = options.nice;
+    let cpus = match &options.cpus {
+        Some(cpus) => Some(cpu_set(cpus)?),
+        None => None,
+    };
+
+    unsafe {
+        cmd.pre_exec(move || {
+            for (resource, limit) in rlimits.iter() {
+                let result = match resource {
+                    Rlimit::NoFile => libc::setrlimit(libc::RLIMIT_NOFILE, limit),
+                    Rlimit::AddressSpace => libc::setrlimit(libc::RLIMIT_AS, limit),
+                    Rlimit::Core => libc::setrlimit(libc::RLIMIT_CORE, limit),
+                    Rlimit::Cpu => libc::setrlimit(libc::RLIMIT_CPU, limit),
+                };
+                if result == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if let Some(nice) = nice {
+                if libc::setpriority(libc::PRIO_PROCESS, 0, nice) == -1 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            if let Some(set) = &cpus {
+                set_affinity(set)?;
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+type CpuSet = libc::cpu_set_t;
+
+/// CPU affinity is only supported on Linux, so no set can be built.
+#[cfg(not(target_os = "linux"))]
+type CpuSet = std::convert::Infallible;
+
+#[cfg(target_os = "linux")]
+fn cpu_set(cpus: &[usize]) -> io::Result<CpuSet> {
+    let invalid = |message: &str| io::Error::new(io::ErrorKind::InvalidInput, message);
+    if cpus.is_empty() {
+        return Err(invalid("CPU set is empty"));
+    }
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    for &cpu in cpus.iter() {
+        if cpu >= libc::CPU_SETSIZE as usize {
+            return Err(invalid("CPU index is out of range"));
+        }
+        unsafe { libc::CPU_SET(cpu, &mut set) };
+    }
+    Ok(set)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn cpu_set(_cpus: &[usize]) -> io::Result<CpuSet> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "CPU affinity is only supported on Linux",
+    ))
+}
+
+/// Restrict the calling process to `set`. Runs in `pre_exec`.
+#[cfg(target_os = "linux")]
+fn set_affinity(set: &CpuSet) -> io::Result<()> {
+    if unsafe { libc::sched_setaffinity(0, mem::size_of::<CpuSet>(), set) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn set_affinity(set: &CpuSet) -> io::Result<()> {
+    match *set {}
+}
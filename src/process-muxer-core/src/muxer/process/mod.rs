@@ -1 +1,18 @@
+pub(crate) mod activation;
+pub(crate) mod cgroup;
+pub(crate) mod coredump;
+pub(crate) mod deadline;
+pub(crate) mod foreground;
+pub(crate) mod handle;
+pub(crate) mod health;
+pub(crate) mod history;
+pub(crate) mod identity;
+pub(crate) mod limits;
+pub(crate) mod options;
 pub(crate) mod pid;
+pub(crate) mod resources;
+pub(crate) mod restart;
+pub(crate) mod signal;
+pub(crate) mod status;
+pub(crate) mod stop;
+pub(crate) mod watchdog;
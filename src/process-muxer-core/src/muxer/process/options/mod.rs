@@ -0,0 +1,172 @@
+use std::{
+    collections::VecDeque,
+    ffi::OsString,
+    process::{Command, Stdio},
+    time::Instant,
+};
+
+use crate::muxer::process::activation::Listener;
+use crate::muxer::process::cgroup::Cgroup;
+use crate::muxer::process::coredump::CoreDumps;
+use crate::muxer::process::deadline::Deadline;
+use crate::muxer::process::handle::{ChildState, Latest};
+use crate::muxer::process::health::HealthCheck;
+use crate::muxer::process::identity::Identity;
+use crate::muxer::process::limits::{Limit, Rlimit};
+use crate::muxer::process::resources::ResourceLimits;
+use crate::muxer::process::restart::RestartPolicy;
+use crate::muxer::process::stop::Stop;
+use crate::muxer::process::watchdog::Watchdog;
+
+/// How one of a child's standard streams is connected. Unlike `Stdio`, it can
+/// be applied again when the child is restarted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StdioMode {
+    #[default]
+    Inherit,
+    Null,
+    /// A pipe to the muxer. Output is reported as `Event::ChildWrote`, and
+    /// stdin is returned in `ChildInfo`.
+    Piped,
+}
+
+impl StdioMode {
+    fn stdio(self) -> Stdio {
+        match self {
+            StdioMode::Inherit => Stdio::inherit(),
+            StdioMode::Null => Stdio::null(),
+            StdioMode::Piped => Stdio::piped(),
+        }
+    }
+}
+
+/// Muxer-specific settings for a spawned child, applied on top of its
+/// `Command`.
+///
+/// A restarted child is spawned from a `Command` rebuilt from the program,
+/// arguments, working directory and environment changes of the original, and
+/// these options. Settings that `Command` does not expose, such as
+/// `pre_exec`, `arg0` or `uid`, are not repeated; use the options here, e.g.
+/// `identity`, for children that restart.
+#[derive(Debug, Clone, Default)]
+pub struct SpawnOptions {
+    /// A name for the child used in events in place of its program path,
+    /// e.g. "web" or "worker-1". Defaults to the program path.
+    pub label: Option<String>,
+    /// Put the child in this process group, as
+    /// `CommandExt::process_group`. 0 creates a new group. Unlike setting it
+    /// on the `Command`, this is preserved across restarts.
+    pub process_group: Option<i32>,
+    pub restart: RestartPolicy,
+    pub health: Option<HealthCheck>,
+    /// Create a datagram socket for the child and pass its path in
+    /// `NOTIFY_SOCKET`, so that it can report readiness and status with the
+    /// systemd notify protocol.
+    pub notify: bool,
+    /// Listening sockets passed to the child as fds 3, 4, ... with
+    /// `LISTEN_FDS`, `LISTEN_FDNAMES` and `LISTEN_PID` set. They stay open in
+    /// the muxer, so connections queue while the child restarts. The child is
+    /// started through `/bin/sh` to set `LISTEN_PID`, which rebuilds its
+    /// command as a restart does.
+    pub listeners: Vec<Listener>,
+    pub watchdog: Option<Watchdog>,
+    pub deadline: Option<Deadline>,
+    pub resources: Option<ResourceLimits>,
+    pub rlimits: Vec<(Rlimit, Limit)>,
+    /// The child's nice level, from -20 to 19.
+    pub nice: Option<i32>,
+    /// The CPUs the child may run on, by index. Only supported on Linux.
+    pub cpus: Option<Vec<usize>>,
+    pub cgroup: Option<Cgroup>,
+    pub identity: Option<Identity>,
+    pub core_dumps: CoreDumps,
+    /// Run the child as a foreground job: in a process group of its own that
+    /// owns the muxer's terminal until the child stops or exits, so that
+    /// interactive programs work. Without a terminal in the foreground, the
+    /// child is spawned normally.
+    pub foreground: bool,
+    /// Pipe the child's stdin and keep it in the muxer, so that the child
+    /// receives input forwarded with `Muxer::forward_stdin`.
+    pub stdin_input: bool,
+    /// How `Muxer::stop_child` asks the child to exit, `SIGTERM` by default.
+    pub stop: Stop,
+    /// The child's standard streams. These replace any set on the `Command`,
+    /// which cannot be repeated on restart. `stdin_input` implies a piped
+    /// stdin.
+    pub stdin: StdioMode,
+    pub stdout: StdioMode,
+    pub stderr: StdioMode,
+    /// Start the child with only the environment set on its `Command`. Unlike
+    /// `Command::env_clear`, this is preserved across restarts.
+    pub env_clear: bool,
+}
+
+impl SpawnOptions {
+    pub fn labeled(label: impl Into<String>) -> Self {
+        Self {
+            label: Some(label.into()),
+            ..Self::default()
+        }
+    }
+}
+
+/// Everything needed to spawn a child again after it exits.
+#[derive(Debug)]
+pub(crate) struct ChildSpec {
+    pub(crate) options: SpawnOptions,
+    /// The number of consecutive restarts so far.
+    pub(crate) attempt: u32,
+    /// When recent unsuccessful exits happened, for crash-loop detection.
+    pub(crate) failures: VecDeque<Instant>,
+    /// The child most recently spawned from this spec.
+    pub(crate) latest: Latest,
+}
+
+impl ChildSpec {
+    pub(crate) fn new(options: SpawnOptions) -> Self {
+        Self {
+            options,
+            attempt: 0,
+            failures: VecDeque::new(),
+            latest: Latest::default(),
+        }
+    }
+
+    /// Rebuild the command that spawned `previous`, without the settings
+    /// applied by `apply`.
+    pub(crate) fn command(&self, previous: &ChildState) -> Command {
+        let mut cmd = Command::new(&previous.prog_path);
+        cmd.args(&previous.args);
+        if let Some(cwd) = &previous.cwd {
+            cmd.current_dir(cwd);
+        }
+        for (key, value) in previous.envs.iter() {
+            match value {
+                Some(value) => cmd.env(key, value),
+                None => cmd.env_remove(key),
+            };
+        }
+        cmd
+    }
+
+    /// Apply the environment clearing and stdio of the options to `cmd`.
+    /// `envs` are the environment changes recorded from the original command,
+    /// which clearing the environment discards.
+    pub(crate) fn apply(&self, cmd: &mut Command, envs: &[(OsString, Option<OsString>)]) {
+        if self.options.env_clear {
+            cmd.env_clear();
+            for (key, value) in envs {
+                if let Some(value) = value {
+                    cmd.env(key, value);
+                }
+            }
+        }
+        let stdin = match self.options.stdin_input {
+            true => StdioMode::Piped,
+            false => self.options.stdin,
+        };
+        cmd.stdin(stdin.stdio());
+        cmd.stdout(self.options.stdout.stdio());
+        cmd.stderr(self.options.stderr.stdio());
+    }
+}
@@ -0,0 +1,185 @@
+use std::{
+    fs, io,
+    time::{Duration, Instant},
+};
+
+use crate::muxer::notice::Notice;
+use crate::muxer::{Muxer, Timeout};
+use crate::Pid;
+
+/// Periodic sampling of a child's memory and CPU usage from `/proc`, with
+/// optional limits. A child over a limit is killed with `SIGKILL`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceLimits {
+    pub interval: Duration,
+    /// The largest resident set size allowed, in bytes.
+    pub max_rss: Option<u64>,
+    /// The largest CPU usage allowed, in cores, e.g. 1.0 for one fully busy
+    /// core.
+    pub max_cpu: Option<f64>,
+    /// The number of consecutive samples over `max_cpu` that count as
+    /// sustained usage.
+    pub cpu_samples: u32,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(1),
+            max_rss: None,
+            max_cpu: None,
+            cpu_samples: 5,
+        }
+    }
+}
+
+/// The resource a child exceeded its `ResourceLimits` for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    Memory,
+    Cpu,
+}
+
+/// Resource-sampling bookkeeping for one child.
+#[derive(Debug, Default)]
+pub(crate) struct ResourceState {
+    /// When the previous sample was taken, and the CPU time in clock ticks
+    /// the child had used by then.
+    previous: Option<(Instant, u64)>,
+    /// Consecutive samples over `max_cpu`.
+    over_cpu: u32,
+}
+
+impl Muxer {
+    /// Sample the resource usage of `pid`, enforce its limits and schedule
+    /// the next sample.
+    pub(crate) fn sample_resources(&mut self, pid: Pid) {
+        let Some(child) = self.children.get_mut(&pid) else {
+            return;
+        };
+        let Some(limits) = child.spec.options.resources.clone() else {
+            return;
+        };
+        let state = child.handle.state.clone();
+        let now = Instant::now();
+        let Ok((rss, ticks)) = read_usage(pid) else {
+            // The child has exited and is about to be reaped.
+            return;
+        };
+        let (since, ticks_before) = child.resources.previous.unwrap_or((state.started_at, 0));
+        child.resources.previous = Some((now, ticks));
+        let elapsed = now.saturating_duration_since(since).as_secs_f64();
+        let cpu = match elapsed > 0.0 {
+            true => ticks.saturating_sub(ticks_before) as f64 / clock_ticks() / elapsed,
+            false => 0.0,
+        };
+
+        self.notices.push_back(Notice::ResourceSample {
+            child: state.clone(),
+            rss,
+            cpu,
+        });
+        let over_cpu = match limits.max_cpu {
+            Some(max_cpu) if cpu > max_cpu => child.resources.over_cpu + 1,
+            _ => 0,
+        };
+        child.resources.over_cpu = over_cpu;
+        let exceeded = if limits.max_rss.is_some_and(|max_rss| rss > max_rss) {
+            Some(Resource::Memory)
+        } else if over_cpu >= limits.cpu_samples.max(1) {
+            Some(Resource::Cpu)
+        } else {
+            None
+        };
+        if let Some(resource) = exceeded {
+            let _ = child.handle.kill();
+            self.notices.push_back(Notice::ResourceLimitExceeded {
+                child: state,
+                resource,
+            });
+            return;
+        }
+
+        self.timers
+            .schedule(now + limits.interval, Timeout::SampleResources { pid });
+    }
+}
+
+/// Read the resident set size in bytes from `/proc/<pid>/status`, and the
+/// user and system CPU time in clock ticks from `/proc/<pid>/stat`.
+fn read_usage(pid: Pid) -> io::Result<(u64, u64)> {
+    let invalid = || io::Error::from(io::ErrorKind::InvalidData);
+    let status = fs::read_to_string(format!("/proc/{}/status", pid.inner))?;
+    let rss = parse_rss(&status).ok_or_else(invalid)?;
+    let stat = fs::read_to_string(format!("/proc/{}/stat", pid.inner))?;
+    let ticks = parse_cpu_ticks(&stat).ok_or_else(invalid)?;
+    Ok((rss, ticks))
+}
+
+/// The `VmRSS` of a `/proc/<pid>/status` file, in bytes.
+fn parse_rss(status: &str) -> Option<u64> {
+    let kb: u64 = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.trim().strip_suffix("kB"))
+        .and_then(|kb| kb.trim().parse().ok())?;
+    Some(kb * 1024)
+}
+
+/// The user plus system CPU time of a `/proc/<pid>/stat` file, in clock
+/// ticks.
+fn parse_cpu_ticks(stat: &str) -> Option<u64> {
+    // The command name may contain spaces and parentheses, so fields are
+    // counted from the last ')'. utime and stime are fields 14 and 15.
+    let (_, fields) = stat.rsplit_once(')')?;
+    let mut fields = fields.split_whitespace().skip(11);
+    let mut field = || -> Option<u64> { fields.next()?.parse().ok() };
+    Some(field()? + field()?)
+}
+
+fn clock_ticks() -> f64 {
+    match unsafe { libc::sysconf(libc::_SC_CLK_TCK) } {
+        ticks if ticks > 0 => ticks as f64,
+        _ => 100.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `/proc/<pid>/stat` line for `comm`, with utime 120 and stime 34.
+    fn stat(comm: &str) -> String {
+        format!(
+            "4242 ({comm}) S 1 4242 4242 0 -1 4194560 1234 0 0 0 120 34 0 0 20 0 1 0 \
+             5678 12345678 910 18446744073709551615 1 1 0 0 0 0 0 0 0 0 0 0 17 3 0 0 0 0 0\n"
+        )
+    }
+
+    #[test]
+    fn cpu_ticks_of_a_plain_command_name() {
+        assert_eq!(parse_cpu_ticks(&stat("server")), Some(154));
+    }
+
+    #[test]
+    fn cpu_ticks_of_command_names_with_spaces_and_parentheses() {
+        assert_eq!(parse_cpu_ticks(&stat("my server")), Some(154));
+        assert_eq!(parse_cpu_ticks(&stat("a) 1 2 3 (b")), Some(154));
+        assert_eq!(parse_cpu_ticks(&stat(")")), Some(154));
+        assert_eq!(parse_cpu_ticks(&stat("((")), Some(154));
+    }
+
+    #[test]
+    fn cpu_ticks_of_a_truncated_stat() {
+        assert_eq!(parse_cpu_ticks("4242 (server) S 1 4242"), None);
+        assert_eq!(parse_cpu_ticks("4242 server"), None);
+    }
+
+    #[test]
+    fn rss_in_bytes() {
+        let status = "Name:\tserver\nVmPeak:\t  20000 kB\nVmRSS:\t    1500 kB\nThreads:\t1\n";
+        assert_eq!(parse_rss(status), Some(1500 * 1024));
+        // Kernel threads have no memory of their own.
+        assert_eq!(parse_rss("Name:\tkthreadd\nThreads:\t1\n"), None);
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// When a child should be restarted after it exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Restart {
+    #[default]
+    Never,
+    /// Restart if the child exits unsuccessfully or is killed by a signal.
+    OnFailure,
+    Always,
+}
+
+/// How the muxer restarts a child after it exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartPolicy {
+    pub restart: Restart,
+    /// Give up after this many consecutive restarts. `None` never gives up.
+    pub max_retries: Option<u32>,
+    /// The delay before the first restart; it doubles with each attempt.
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+    /// A child that ran at least this long resets the attempt count.
+    pub reset_after: Duration,
+    /// Stop restarting a child that keeps failing. `None` disables detection.
+    pub crash_loop: Option<CrashLoop>,
+}
+
+/// Detects a child that keeps failing shortly after it is restarted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrashLoop {
+    /// The number of unsuccessful exits that mark a child as crash-looping...
+    pub failures: u32,
+    /// ...if they all happen within this window.
+    pub window: Duration,
+    /// The number of trailing stderr lines reported in `Event::CrashLoop`.
+    pub stderr_lines: usize,
+}
+
+impl Default for CrashLoop {
+    fn default() -> Self {
+        Self {
+            failures: 5,
+            window: Duration::from_secs(30),
+            stderr_lines: 10,
+        }
+    }
+}
+
+impl CrashLoop {
+    /// Record a failure at `at`, forgetting failures that have left the
+    /// window. Returns true if the child is now crash-looping.
+    pub(crate) fn record(&self, failures: &mut VecDeque<Instant>, at: Instant) -> bool {
+        failures.push_back(at);
+        while failures
+            .front()
+            .is_some_and(|first| at.duration_since(*first) > self.window)
+        {
+            failures.pop_front();
+        }
+        failures.len() >= self.failures as usize
+    }
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            restart: Restart::Never,
+            max_retries: None,
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            reset_after: Duration::from_secs(10),
+            crash_loop: Some(CrashLoop::default()),
+        }
+    }
+}
+
+impl RestartPolicy {
+    pub fn on_failure() -> Self {
+        Self {
+            restart: Restart::OnFailure,
+            ..Self::default()
+        }
+    }
+
+    pub fn always() -> Self {
+        Self {
+            restart: Restart::Always,
+            ..Self::default()
+        }
+    }
+
+    /// Whether a child that exited with `success` should be restarted at all.
+    pub(crate) fn wants_restart(&self, success: bool) -> bool {
+        match self.restart {
+            Restart::Never => false,
+            Restart::OnFailure => !success,
+            Restart::Always => true,
+        }
+    }
+
+    /// The backoff before restart number `attempt`, counting from 1.
+    pub(crate) fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
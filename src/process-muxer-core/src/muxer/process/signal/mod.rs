@@ -0,0 +1,99 @@
+use std::fmt;
+
+/// A unix signal that can be received by the muxer or sent to a child.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Signal {
+    Hangup,
+    Interrupt,
+    Quit,
+    Kill,
+    User1,
+    User2,
+    Terminate,
+    Continue,
+    Stop,
+    /// Any signal without a dedicated variant, by number.
+    Other(i32),
+}
+
+impl Signal {
+    /// The raw signal number.
+    pub fn as_raw(self) -> i32 {
+        match self {
+            Signal::Hangup => libc::SIGHUP,
+            Signal::Interrupt => libc::SIGINT,
+            Signal::Quit => libc::SIGQUIT,
+            Signal::Kill => libc::SIGKILL,
+            Signal::User1 => libc::SIGUSR1,
+            Signal::User2 => libc::SIGUSR2,
+            Signal::Terminate => libc::SIGTERM,
+            Signal::Continue => libc::SIGCONT,
+            Signal::Stop => libc::SIGSTOP,
+            Signal::Other(signum) => signum,
+        }
+    }
+
+    /// Build a `Signal` from a raw signal number.
+    pub fn from_raw(signum: i32) -> Self {
+        match signum {
+            libc::SIGHUP => Signal::Hangup,
+            libc::SIGINT => Signal::Interrupt,
+            libc::SIGQUIT => Signal::Quit,
+            libc::SIGKILL => Signal::Kill,
+            libc::SIGUSR1 => Signal::User1,
+            libc::SIGUSR2 => Signal::User2,
+            libc::SIGTERM => Signal::Terminate,
+            libc::SIGCONT => Signal::Continue,
+            libc::SIGSTOP => Signal::Stop,
+            _ => Signal::Other(signum),
+        }
+    }
+
+    /// The conventional name of the signal, e.g. "SIGKILL", if it is a
+    /// standard one.
+    pub fn name(self) -> Option<&'static str> {
+        let name = match self.as_raw() {
+            libc::SIGHUP => "SIGHUP",
+            libc::SIGINT => "SIGINT",
+            libc::SIGQUIT => "SIGQUIT",
+            libc::SIGILL => "SIGILL",
+            libc::SIGTRAP => "SIGTRAP",
+            libc::SIGABRT => "SIGABRT",
+            libc::SIGBUS => "SIGBUS",
+            libc::SIGFPE => "SIGFPE",
+            libc::SIGKILL => "SIGKILL",
+            libc::SIGUSR1 => "SIGUSR1",
+            libc::SIGSEGV => "SIGSEGV",
+            libc::SIGUSR2 => "SIGUSR2",
+            libc::SIGPIPE => "SIGPIPE",
+            libc::SIGALRM => "SIGALRM",
+            libc::SIGTERM => "SIGTERM",
+            libc::SIGCHLD => "SIGCHLD",
+            libc::SIGCONT => "SIGCONT",
+            libc::SIGSTOP => "SIGSTOP",
+            libc::SIGTSTP => "SIGTSTP",
+            libc::SIGTTIN => "SIGTTIN",
+            libc::SIGTTOU => "SIGTTOU",
+            libc::SIGURG => "SIGURG",
+            libc::SIGXCPU => "SIGXCPU",
+            libc::SIGXFSZ => "SIGXFSZ",
+            libc::SIGVTALRM => "SIGVTALRM",
+            libc::SIGPROF => "SIGPROF",
+            libc::SIGWINCH => "SIGWINCH",
+            libc::SIGIO => "SIGIO",
+            libc::SIGSYS => "SIGSYS",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+/// Formats as the signal's name, or as "signal N" if it has none.
+impl fmt::Display for Signal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => f.write_str(name),
+            None => write!(f, "signal {}", self.as_raw()),
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::{cell::Cell, os::unix::process::ExitStatusExt, process::ExitStatus, time::Duration};
+
+use crate::{FdTag, Pid, Signal};
+
+/// Whether a child is running, stopped by a signal, or has exited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunState {
+    Running,
+    Stopped,
+    Exited,
+}
+
+/// How a child terminated, from its `ExitStatus`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    /// The child exited with this code.
+    Exited(i32),
+    /// The child was killed by a signal, and dumped core if the flag is set.
+    Signaled(Signal, bool),
+}
+
+impl From<ExitStatus> for Termination {
+    fn from(exit_status: ExitStatus) -> Self {
+        match (exit_status.code(), exit_status.signal()) {
+            (Some(code), _) => Termination::Exited(code),
+            (None, Some(signum)) => {
+                Termination::Signaled(Signal::from_raw(signum), exit_status.core_dumped())
+            }
+            // Stopped and continued statuses are never reported as a
+            // termination.
+            (None, None) => Termination::Exited(exit_status.into_raw()),
+        }
+    }
+}
+
+/// Output statistics for one of a child's piped streams.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StreamStatus {
+    pub bytes: u64,
+    pub lines: u64,
+    /// False if the stream was never piped or has been closed.
+    pub open: bool,
+}
+
+/// A point-in-time snapshot of a child, as returned by `Muxer::status`.
+#[derive(Debug, Clone)]
+pub struct ChildStatus {
+    pub pid: Pid,
+    pub label: String,
+    pub state: RunState,
+    /// Time since spawn, or the total run time if the child has exited.
+    pub uptime: Duration,
+    pub exit_status: Option<ExitStatus>,
+    /// The child kept failing and will not be restarted.
+    pub crash_looping: bool,
+    /// The result of the child's health check, if it has one.
+    pub healthy: Option<bool>,
+    /// Whether the child has sent `READY=1` over `NOTIFY_SOCKET`.
+    pub ready: bool,
+    /// The latest `STATUS=` text sent over `NOTIFY_SOCKET`.
+    pub notify_status: Option<String>,
+    pub stdout: StreamStatus,
+    pub stderr: StreamStatus,
+}
+
+/// Live counters for a child's stream, updated as the muxer reads output.
+#[derive(Debug, Default)]
+pub(crate) struct StreamCounters {
+    bytes: Cell<u64>,
+    lines: Cell<u64>,
+    open: Cell<bool>,
+}
+
+impl StreamCounters {
+    pub(crate) fn opened(&self) {
+        self.open.set(true);
+    }
+
+    pub(crate) fn closed(&self) {
+        self.open.set(false);
+    }
+
+    pub(crate) fn record_line(&self, line: &str) {
+        self.bytes.set(self.bytes.get() + line.len() as u64);
+        self.lines.set(self.lines.get() + 1);
+    }
+
+    pub(crate) fn snapshot(&self) -> StreamStatus {
+        StreamStatus {
+            bytes: self.bytes.get(),
+            lines: self.lines.get(),
+            open: self.open.get(),
+        }
+    }
+}
+
+/// Per-stream counters for a child.
+#[derive(Debug, Default)]
+pub(crate) struct OutputCounters {
+    pub(crate) stdout: StreamCounters,
+    pub(crate) stderr: StreamCounters,
+}
+
+impl OutputCounters {
+    pub(crate) fn get(&self, tag: FdTag) -> &StreamCounters {
+        match tag {
+            FdTag::Stdout => &self.stdout,
+            FdTag::Stderr => &self.stderr,
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    ffi::OsString,
+    io,
+    process::{Child, Command, Stdio},
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::muxer::notice::Notice;
+use crate::muxer::process::foreground::Terminal;
+use crate::muxer::process::handle::ChildState;
+use crate::muxer::{Muxer, MuxerChild, Timeout};
+use crate::{Pid, Signal};
+
+/// How long a stop command may run when the child has no grace period.
+const STOP_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How a child is asked to exit by `Muxer::stop_child`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StopMethod {
+    Signal(Signal),
+    /// Write this to the child's stdin, e.g. "quit\n", and close it. The
+    /// child must be spawned with `SpawnOptions::stdin_input`.
+    Stdin(Vec<u8>),
+    /// Run a command, e.g. `redis-cli shutdown`. A command that fails, or
+    /// is still running after the grace period (10 seconds without one), is
+    /// killed and reported with `Event::StopCommandFailed`.
+    Command {
+        program: OsString,
+        args: Vec<OsString>,
+    },
+}
+
+impl StopMethod {
+    pub fn command<I, S>(program: impl Into<OsString>, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        StopMethod::Command {
+            program: program.into(),
+            args: args.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl Default for StopMethod {
+    fn default() -> Self {
+        StopMethod::Signal(Signal::Terminate)
+    }
+}
+
+/// How to stop a child: the method, and optionally how long to wait for it
+/// to exit before sending `SIGKILL`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stop {
+    pub method: StopMethod,
+    pub grace: Option<Duration>,
+}
+
+impl Stop {
+    pub fn new(method: StopMethod) -> Self {
+        Self {
+            method,
+            grace: None,
+        }
+    }
+}
+
+/// A running stop command and the child it stops.
+#[derive(Debug)]
+pub(crate) struct StopCommand {
+    target: Rc<ChildState>,
+    command: Child,
+    kill_at: Instant,
+}
+
+impl Muxer {
+    /// Ask `pid` to exit with its `SpawnOptions::stop` method, and schedule
+    /// the kill if it has a grace period. Stop commands run with their output
+    /// discarded.
+    pub fn stop_child(&mut self, pid: Pid) -> io::Result<()> {
+        self.stop_child_within(pid, None)
+    }
+
+    /// Like `stop_child`, with a grace period for children that have none.
+    /// The kill is scheduled even if the stop method fails, so that it also
+    /// covers whatever the caller falls back to.
+    pub fn stop_child_within(
+        &mut self,
+        pid: Pid,
+        default_grace: Option<Duration>,
+    ) -> io::Result<()> {
+        let Some(child) = self.children.get_mut(&pid) else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no running child with pid {pid}"),
+            ));
+        };
+        let stop = child.spec.options.stop.clone();
+        let target = child.handle.state.clone();
+        let command = run_stop_method(child, &stop.method, self.terminal.as_ref());
+        let grace = stop.grace.or(default_grace);
+        if let Some(grace) = grace {
+            let kill_at = Instant::now() + grace;
+            let child = target.clone();
+            self.timers.schedule(kill_at, Timeout::Kill { child });
+        }
+        if let Some(command) = command? {
+            let kill_at = Instant::now() + grace.unwrap_or(STOP_COMMAND_TIMEOUT);
+            self.timers.schedule(kill_at, Timeout::StopCommands);
+            self.stop_commands.push(StopCommand {
+                target,
+                command,
+                kill_at,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reap stop commands that have exited, and kill those that have run too
+    /// long. Both failures are reported.
+    pub(crate) fn reap_stop_commands(&mut self) {
+        let now = Instant::now();
+        let mut failed = Vec::new();
+        self.stop_commands.retain_mut(|stop| {
+            let exit_status = match stop.command.try_wait() {
+                Ok(Some(exit_status)) if exit_status.success() => return false,
+                Ok(Some(exit_status)) => Some(exit_status),
+                Ok(None) if now >= stop.kill_at => {
+                    let _ = stop.command.kill();
+                    let _ = stop.command.wait();
+                    None
+                }
+                Ok(None) => return true,
+                Err(_) => return false,
+            };
+            failed.push(Notice::StopCommandFailed {
+                child: stop.target.clone(),
+                exit_status,
+            });
+            false
+        });
+        self.notices.extend(failed);
+    }
+}
+
+/// Ask `child` to exit with `method`. Returns the stop command, if one was
+/// started.
+fn run_stop_method(
+    child: &mut MuxerChild,
+    method: &StopMethod,
+    terminal: Option<&Terminal>,
+) -> io::Result<Option<Child>> {
+    match method {
+        StopMethod::Signal(signal) => child.handle.signal(*signal)?,
+        StopMethod::Stdin(payload) => {
+            if !child.spec.options.stdin_input {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the child was not spawned with stdin_input",
+                ));
+            }
+            let Some(mut stdin) = child.stdin.take() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "the child's stdin is not kept by the muxer",
+                ));
+            };
+            if !stdin.send(payload)? {
+                return Err(io::Error::new(
+                    io::ErrorKind::WouldBlock,
+                    "the child is not reading its stdin",
+                ));
+            }
+        }
+        StopMethod::Command { program, args } => {
+            let mut cmd = Command::new(program);
+            cmd.args(args)
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null());
+            if let Some(terminal) = terminal {
+                terminal.restore_sigttou(&mut cmd);
+            }
+            return Ok(Some(cmd.spawn()?));
+        }
+    }
+    Ok(None)
+}
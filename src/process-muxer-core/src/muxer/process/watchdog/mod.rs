@@ -0,0 +1,69 @@
+use std::time::{Duration, Instant};
+
+use crate::muxer::notice::Notice;
+use crate::muxer::{Muxer, Timeout};
+use crate::{Pid, Signal};
+
+/// An inactivity timeout for a child. The child is considered alive while it
+/// writes lines to a piped stdout or stderr, or sends `WATCHDOG=1` to its
+/// notify socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchdog {
+    pub timeout: Duration,
+    pub action: WatchdogAction,
+}
+
+impl Watchdog {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            action: WatchdogAction::default(),
+        }
+    }
+}
+
+/// What to do when a child's `Watchdog` expires, besides emitting
+/// `Event::WatchdogExpired`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WatchdogAction {
+    /// Only emit the event.
+    #[default]
+    Report,
+    Signal(Signal),
+    /// Restart the child, regardless of its `RestartPolicy`.
+    Restart,
+}
+
+impl Muxer {
+    /// Check the watchdog of `pid`, and schedule the next check for when it
+    /// could next expire. A child that stays silent expires again after each
+    /// further timeout.
+    pub(crate) fn check_watchdog(&mut self, pid: Pid) {
+        let Some(child) = self.children.get(&pid) else {
+            return;
+        };
+        let Some(watchdog) = child.spec.options.watchdog.clone() else {
+            return;
+        };
+        let state = child.handle.state.clone();
+        let now = Instant::now();
+        let silent = now.saturating_duration_since(state.last_activity.get());
+        if silent < watchdog.timeout {
+            let next = now + (watchdog.timeout - silent);
+            self.timers.schedule(next, Timeout::Watchdog { pid });
+            return;
+        }
+
+        self.notices.push_back(Notice::WatchdogExpired {
+            child: state.clone(),
+            silent,
+        });
+        self.timers
+            .schedule(now + watchdog.timeout, Timeout::Watchdog { pid });
+        let _ = match watchdog.action {
+            WatchdogAction::Report => Ok(()),
+            WatchdogAction::Signal(signal) => child.handle.signal(signal),
+            WatchdogAction::Restart => self.restart_child(pid),
+        };
+    }
+}
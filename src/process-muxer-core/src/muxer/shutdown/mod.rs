@@ -0,0 +1,70 @@
+use crate::muxer::notice::Notice;
+use crate::muxer::Muxer;
+
+/// How far a shutdown started with `Muxer::begin_shutdown` has escalated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownStage {
+    /// The children are being stopped and the muxer waits for them to exit.
+    Graceful,
+    /// SIGINT was received during the shutdown and every remaining child was
+    /// killed with `ChildHandle::kill`.
+    Forced,
+    /// SIGINT was received again. The muxer has taken the terminal back,
+    /// and the caller should exit without waiting for the children.
+    Abandoned,
+}
+
+impl Muxer {
+    /// Mark the start of a shutdown, disabling restarts. Stopping the
+    /// children is left to the caller. Until the last child has exited, SIGINT
+    /// is not reported as `Event::SignalReceived`: the first one sends SIGKILL
+    /// to every remaining child, and the next one abandons the shutdown. The
+    /// muxer never exits the process itself: on `ShutdownStage::Abandoned`
+    /// the caller should stop pumping and exit, e.g. with status 130. Each
+    /// stage is reported as `Event::Shutdown`. Does nothing if there are no
+    /// children.
+    pub fn begin_shutdown(&mut self) {
+        self.set_restarts_enabled(false);
+        if self.shutdown.is_some() || self.children.is_empty() {
+            return;
+        }
+        self.enter_shutdown_stage(ShutdownStage::Graceful);
+    }
+
+    /// The stage of the shutdown in progress, if any.
+    pub fn shutdown_stage(&self) -> Option<ShutdownStage> {
+        self.shutdown
+    }
+
+    fn enter_shutdown_stage(&mut self, stage: ShutdownStage) {
+        self.shutdown = Some(stage);
+        self.notices.push_back(Notice::Shutdown { stage });
+    }
+
+    /// Escalate the shutdown in progress on SIGINT. Returns false if there is
+    /// none.
+    pub(crate) fn interrupt_shutdown(&mut self) -> bool {
+        match self.shutdown {
+            None => false,
+            Some(ShutdownStage::Graceful) => {
+                for child in self.children.values() {
+                    let _ = child.handle.kill();
+                }
+                self.enter_shutdown_stage(ShutdownStage::Forced);
+                true
+            }
+            Some(ShutdownStage::Forced | ShutdownStage::Abandoned) => {
+                self.reclaim_terminal();
+                self.enter_shutdown_stage(ShutdownStage::Abandoned);
+                true
+            }
+        }
+    }
+
+    /// End the shutdown once its last child has been reaped.
+    pub(crate) fn shutdown_progress(&mut self) {
+        if self.children.is_empty() {
+            self.shutdown = None;
+        }
+    }
+}
@@ -1,13 +1,12 @@
 use std::{
     io::{self, BufReader},
-    path::PathBuf,
     process::{ChildStderr, ChildStdout},
     rc::Rc,
 };
 
 use mio::{event::Source, unix::pipe, Interest, Token};
 
-use crate::Pid;
+use crate::muxer::process::handle::ChildState;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FdTag {
@@ -17,8 +16,7 @@ pub enum FdTag {
 
 #[derive(Debug)]
 pub struct ChildOut {
-    pub pid: Pid,
-    pub prog_path: Rc<PathBuf>,
+    pub child: Rc<ChildState>,
     pub tag: FdTag,
     pub buf: String,
     pub fd: BufReader<pipe::Receiver>,
@@ -27,15 +25,13 @@ pub struct ChildOut {
 impl ChildOut {
     pub(crate) fn from_pipe<T: Into<pipe::Receiver> + TaggedFd>(
         value: T,
-        pid: Pid,
-        prog_path: Rc<PathBuf>,
+        child: Rc<ChildState>,
     ) -> Self {
         let pipe: pipe::Receiver = value.into();
         pipe.set_nonblocking(true)
             .expect("setting nonblocking to succeed");
         ChildOut {
-            pid,
-            prog_path,
+            child,
             tag: T::fdtag(),
             buf: String::with_capacity(1024),
             fd: BufReader::with_capacity(8192, pipe),
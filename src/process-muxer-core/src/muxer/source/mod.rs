@@ -1,9 +1,10 @@
 pub(crate) mod childout;
+pub(crate) mod notify;
 #[cfg(feature = "signals")]
 pub(crate) mod signal;
+pub(crate) mod stdin;
 pub(crate) mod termination;
 
-#[cfg(feature = "signals")]
 pub enum EventStream<T> {
     Emit(T),
     Drained(SourceInstruction),
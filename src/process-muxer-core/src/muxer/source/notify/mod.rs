@@ -0,0 +1,137 @@
+use std::{
+    env, fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+use mio::{event::Source, net::UnixDatagram, Interest, Token};
+
+use crate::muxer::process::handle::ChildState;
+use crate::muxer::source::{EventStream, SourceInstruction};
+
+/// A datagram socket implementing the receiving end of systemd's
+/// `NOTIFY_SOCKET` protocol. The socket file is removed on drop.
+#[derive(Debug)]
+pub struct NotifySocket {
+    socket: UnixDatagram,
+    path: PathBuf,
+}
+
+impl NotifySocket {
+    /// Bind a socket at a fresh path in `$XDG_RUNTIME_DIR`, or the temporary
+    /// directory if that is not set.
+    pub fn bind() -> io::Result<Self> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let dir = env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(env::temp_dir);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = dir.join(format!("process-muxer-{}-{n}.notify", std::process::id()));
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e),
+            _ => (),
+        }
+        let socket = UnixDatagram::bind(&path)?;
+        Ok(Self { socket, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for NotifySocket {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Receives notifications sent by one child.
+#[derive(Debug)]
+pub struct NotifySource {
+    pub child: Rc<ChildState>,
+    /// The `KEY=VALUE` assignments of the most recent datagram.
+    pub fields: Vec<(String, String)>,
+    socket: NotifySocket,
+    buf: Vec<u8>,
+}
+
+impl NotifySource {
+    pub fn new(socket: NotifySocket, child: Rc<ChildState>) -> Self {
+        Self {
+            child,
+            fields: Vec::new(),
+            socket,
+            buf: vec![0; 4096],
+        }
+    }
+
+    /// Read the next datagram into `fields`. Once there are none left, the
+    /// source asks to be deregistered if its child has exited.
+    pub fn next(&mut self) -> EventStream<()> {
+        loop {
+            match self.socket.socket.recv(&mut self.buf) {
+                Ok(n) => {
+                    let message = String::from_utf8_lossy(&self.buf[..n]);
+                    self.fields.clear();
+                    self.fields.extend(message.lines().filter_map(|line| {
+                        let (key, value) = line.split_once('=')?;
+                        Some((key.to_owned(), value.to_owned()))
+                    }));
+                    self.record();
+                    return EventStream::Emit(());
+                }
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    let instruction = match self.child.exit_status.get() {
+                        Some(_) => SourceInstruction::Deregister,
+                        None => SourceInstruction::Reregister,
+                    };
+                    return EventStream::Drained(instruction);
+                }
+                Err(e) => panic!("Unexpected error when reading notify socket: {e}"),
+            }
+        }
+    }
+
+    /// Keep the child's readiness and status text up to date.
+    fn record(&self) {
+        for (key, value) in self.fields.iter() {
+            match key.as_str() {
+                "READY" if value == "1" => self.child.ready.set(true),
+                "WATCHDOG" if value == "1" => self.child.last_activity.set(Instant::now()),
+                "STATUS" => {
+                    self.child.notify_status.replace(Some(value.clone()));
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Source for NotifySource {
+    fn register(
+        &mut self,
+        registry: &mio::Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.socket.socket.register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &mio::Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.socket.socket.reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &mio::Registry) -> io::Result<()> {
+        self.socket.socket.deregister(registry)
+    }
+}
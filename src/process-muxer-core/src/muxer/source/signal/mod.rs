@@ -6,16 +6,10 @@ use std::fmt::Debug;
 use std::io;
 
 use crate::muxer::source::SourceInstruction;
+use crate::Signal;
 
 use super::EventStream;
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub enum Signal {
-    Hangup,
-    Interrupt,
-    Terminate,
-}
-
 #[derive(Debug)]
 enum State {
     Waiting,
@@ -68,19 +62,18 @@ impl SignalSource {
         Ok(res)
     }
 
+    /// Listen for `signum` as well.
+    pub fn add_signal(&self, signum: i32) -> io::Result<()> {
+        self.signals.add_signal(signum)
+    }
+
     pub fn next(&mut self) -> EventStream<Signal> {
         loop {
             match &mut self.state {
                 State::Waiting => self.state = State::Draining(self.signals.pending()),
                 State::Draining(ref mut xs) => match xs.next() {
                     Some(signum) => {
-                        let sig = match signum {
-                            SIGHUP => Signal::Hangup,
-                            SIGINT => Signal::Interrupt,
-                            SIGTERM => Signal::Interrupt,
-                            _ => unreachable!("todo"),
-                        };
-                        return EventStream::Emit(sig);
+                        return EventStream::Emit(Signal::from_raw(signum));
                     }
                     None => {
                         self.state = State::Waiting;
@@ -0,0 +1,92 @@
+use std::{io, os::fd::RawFd};
+
+use mio::{event::Source, unix::SourceFd, Interest, Token};
+
+use crate::muxer::source::SourceInstruction;
+
+/// Reads lines from the muxer's own stdin.
+///
+/// Stdin is shared with the rest of the process and possibly other
+/// processes, so it is not made non-blocking. Instead each read is preceded
+/// by a zero-timeout `poll`, which is enough for pipes and terminals.
+#[derive(Debug)]
+pub struct StdinSource {
+    fd: RawFd,
+    /// A partial line waiting for its newline.
+    pending: Vec<u8>,
+    buf: Vec<u8>,
+}
+
+impl StdinSource {
+    pub fn new() -> Self {
+        Self {
+            fd: libc::STDIN_FILENO,
+            pending: Vec::new(),
+            buf: vec![0; 4096],
+        }
+    }
+
+    /// Read everything available into `lines`, without their newlines. At
+    /// the end of input, a trailing partial line is included and the source
+    /// asks to be deregistered.
+    pub fn read_lines(&mut self, lines: &mut Vec<String>) -> SourceInstruction {
+        loop {
+            let mut pollfd = libc::pollfd {
+                fd: self.fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            match unsafe { libc::poll(&mut pollfd, 1, 0) } {
+                1 => (),
+                -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => continue,
+                _ => return SourceInstruction::Reregister,
+            }
+            let n = unsafe { libc::read(self.fd, self.buf.as_mut_ptr().cast(), self.buf.len()) };
+            match n {
+                -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => continue,
+                // Other errors, such as `EIO` or `EAGAIN`, are not the end of
+                // input; the source waits for stdin to be readable again.
+                -1 => return SourceInstruction::Reregister,
+                0 => {
+                    if !self.pending.is_empty() {
+                        lines.push(String::from_utf8_lossy(&self.pending).into_owned());
+                        self.pending.clear();
+                    }
+                    return SourceInstruction::Deregister;
+                }
+                n => {
+                    self.pending.extend_from_slice(&self.buf[..n as usize]);
+                    while let Some(i) = self.pending.iter().position(|b| *b == b'\n') {
+                        let line: Vec<u8> = self.pending.drain(..=i).collect();
+                        let line = String::from_utf8_lossy(&line[..i]);
+                        lines.push(line.trim_end_matches('\r').to_owned());
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Source for StdinSource {
+    fn register(
+        &mut self,
+        registry: &mio::Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.fd).register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &mio::Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.fd).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &mio::Registry) -> io::Result<()> {
+        SourceFd(&self.fd).deregister(registry)
+    }
+}
@@ -2,4 +2,3 @@
 mod imp;
 
 pub use imp::ChildTerminationSource;
-
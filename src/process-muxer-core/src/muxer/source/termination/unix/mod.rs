@@ -1,8 +1,16 @@
-use std::{collections::BTreeMap, io, path::PathBuf, process::ExitStatus, rc::Rc};
+use std::{
+    collections::BTreeMap,
+    io::{self, ErrorKind},
+    os::unix::process::ExitStatusExt,
+    process::ExitStatus,
+    rc::Rc,
+};
 
+use crate::muxer::process::foreground::JobChange;
+use crate::muxer::process::handle::ChildState;
 use crate::muxer::source::SourceInstruction;
 use crate::muxer::MuxerChild;
-use crate::Pid;
+use crate::{Pid, Signal};
 use mio::event::Source;
 use signal_hook_mio::v0_8::Signals;
 
@@ -44,23 +52,58 @@ impl ChildTerminationSource {
     pub fn handle_event(
         &mut self,
         children: &mut BTreeMap<Pid, MuxerChild>,
-        buffer: &mut Vec<(Pid, Rc<PathBuf>, ExitStatus)>,
+        buffer: &mut Vec<MuxerChild>,
+        changes: &mut Vec<(Rc<ChildState>, JobChange)>,
     ) -> SourceInstruction {
         if self.signals.pending().last().is_some() {
-            for (pid, muxer_child) in children.iter_mut() {
-                let child = &mut muxer_child.child;
-                if let Some(exit_status) = child.try_wait().unwrap() {
-                    muxer_child.exit_status.replace(Some(exit_status));
-                    let awaited_child = (*pid, muxer_child.prog_path.clone(), exit_status);
-                    buffer.push(awaited_child);
+            let mut reaped = Vec::new();
+            for (pid, muxer_child) in children.iter() {
+                let state = &muxer_child.handle.state;
+                if let Some(exit_status) = poll_child(state, changes) {
+                    state.reaped(exit_status);
+                    reaped.push(*pid);
                 }
             }
 
-            for (pid, _, _) in buffer.iter() {
-                let _ = children.remove(pid);
+            for pid in reaped {
+                buffer.extend(children.remove(&pid));
             }
         }
 
         SourceInstruction::Reregister
     }
 }
+
+/// Collect every pending state change of a child without blocking. Stops and
+/// continues are recorded on `state` and in `changes`; the exit status is
+/// returned once the child has been reaped.
+fn poll_child(
+    state: &Rc<ChildState>,
+    changes: &mut Vec<(Rc<ChildState>, JobChange)>,
+) -> Option<ExitStatus> {
+    let pid = state.pid.inner as libc::pid_t;
+    loop {
+        let mut status = 0;
+        let flags = libc::WNOHANG | libc::WUNTRACED | libc::WCONTINUED;
+        match unsafe { libc::waitpid(pid, &mut status, flags) } {
+            0 => return None,
+            -1 => {
+                let e = io::Error::last_os_error();
+                match e.kind() {
+                    ErrorKind::Interrupted => continue,
+                    _ => panic!("Unexpected error while waiting for child {pid}: {e}"),
+                }
+            }
+            _ if libc::WIFSTOPPED(status) => {
+                state.stopped.set(true);
+                let signal = Signal::from_raw(libc::WSTOPSIG(status));
+                changes.push((state.clone(), JobChange::Stopped(signal)));
+            }
+            _ if libc::WIFCONTINUED(status) => {
+                state.stopped.set(false);
+                changes.push((state.clone(), JobChange::Continued));
+            }
+            _ => return Some(ExitStatus::from_raw(status)),
+        }
+    }
+}
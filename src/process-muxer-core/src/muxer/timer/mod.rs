@@ -0,0 +1,60 @@
+use std::{collections::BTreeMap, time::Instant};
+
+/// Identifies a scheduled timer so it can be cancelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct TimerKey {
+    at: Instant,
+    seq: u64,
+}
+
+/// A timer set with `Muxer::set_timer`, reported by `Event::TimerFired`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TimerId {
+    pub(crate) key: TimerKey,
+}
+
+/// A queue of deadlines the muxer services between polls. The poll timeout is
+/// the time until the earliest deadline.
+#[derive(Debug)]
+pub(crate) struct Timers<T> {
+    queue: BTreeMap<TimerKey, T>,
+    seq: u64,
+}
+
+impl<T> Timers<T> {
+    pub(crate) fn new() -> Self {
+        Self {
+            queue: BTreeMap::new(),
+            seq: 0,
+        }
+    }
+
+    pub(crate) fn schedule(&mut self, at: Instant, value: T) -> TimerKey {
+        let key = TimerKey { at, seq: self.seq };
+        self.seq += 1;
+        self.queue.insert(key, value);
+        key
+    }
+
+    /// Remove the timer with `key`, returning it if it had not fired yet.
+    pub(crate) fn cancel(&mut self, key: TimerKey) -> Option<T> {
+        self.queue.remove(&key)
+    }
+
+    /// Drop every timer for which `keep` returns false.
+    pub(crate) fn retain<F: FnMut(&T) -> bool>(&mut self, mut keep: F) {
+        self.queue.retain(|_, v| keep(v));
+    }
+
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        self.queue.keys().next().map(|k| k.at)
+    }
+
+    /// Remove and return the earliest timer if it is due at `now`.
+    pub(crate) fn pop_expired(&mut self, now: Instant) -> Option<(TimerKey, T)> {
+        match self.queue.first_entry() {
+            Some(entry) if entry.key().at <= now => Some(entry.remove_entry()),
+            _ => None,
+        }
+    }
+}
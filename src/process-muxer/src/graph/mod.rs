@@ -0,0 +1,277 @@
+use std::{collections::BTreeMap, process::Command, time::Duration};
+
+use regex::Regex;
+
+use crate::{
+    ChildInfo, Error, Event, Muxer, Probe, Result, ShutdownStage, SpawnOptions, CLEANUP_GRACE,
+};
+
+/// When a service counts as started, so that its dependents may be spawned.
+#[derive(Debug, Clone)]
+pub enum Readiness {
+    /// Ready as soon as it has been spawned.
+    Spawned,
+    /// Ready once it writes a line matching the regex, e.g. "ready to accept
+    /// connections".
+    LogMatch(Regex),
+    /// Ready once the probe accepts a connection.
+    Listening(Probe),
+    /// Ready once the service sends `READY=1` over `NOTIFY_SOCKET`. This
+    /// turns on `SpawnOptions::notify` for the service.
+    Notified,
+}
+
+/// How often a `Readiness::Listening` probe is retried.
+const PROBE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A node in a `ServiceGraph`.
+#[derive(Debug)]
+pub struct Service {
+    /// Used to refer to the service in `depends_on`, and as its label.
+    pub name: String,
+    pub command: Command,
+    pub options: SpawnOptions,
+    pub readiness: Readiness,
+    /// Names of services that must be ready before this one is spawned.
+    pub depends_on: Vec<String>,
+}
+
+impl Service {
+    pub fn new(name: impl Into<String>, command: Command) -> Self {
+        let name = name.into();
+        Self {
+            options: SpawnOptions::labeled(name.clone()),
+            name,
+            command,
+            readiness: Readiness::Spawned,
+            depends_on: Vec::new(),
+        }
+    }
+}
+
+/// A set of services spawned in dependency order, each waiting for the
+/// readiness of everything it depends on.
+#[derive(Debug, Default)]
+pub struct ServiceGraph {
+    services: Vec<Service>,
+}
+
+impl ServiceGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, service: Service) {
+        self.services.push(service);
+    }
+
+    /// Spawn every service with `Muxer::forward_with` in topological order,
+    /// waiting for each to become ready before spawning its dependents.
+    /// Services are spawned in the order they were added where dependencies
+    /// allow.
+    ///
+    /// If a service fails to spawn or become ready, the services already
+    /// started keep running; `Muxer::cleanup` stops them.
+    pub fn start(self, muxer: &mut Muxer) -> Result<RunningGraph> {
+        let order = self.order()?;
+        let mut services: Vec<Option<Service>> = self.services.into_iter().map(Some).collect();
+        let mut started = Vec::with_capacity(order.len());
+        for ix in order {
+            let mut service = services[ix]
+                .take()
+                .expect("each service to be ordered once");
+            if let Readiness::Notified = service.readiness {
+                service.options.notify = true;
+            }
+            let child = muxer
+                .forward_with(service.command, service.options)
+                .map_err(|error| Error::ServiceFailed {
+                    service: service.name.clone(),
+                    error,
+                })?;
+            match service.readiness {
+                Readiness::Spawned => (),
+                Readiness::LogMatch(re) => muxer.wait_for_match(&child, re)?,
+                Readiness::Listening(probe) => {
+                    muxer.wait_until_listening(&child, &probe, PROBE_INTERVAL)?
+                }
+                Readiness::Notified => muxer.wait_until_ready(&child)?,
+            }
+            started.push((service.name, child));
+        }
+        Ok(RunningGraph { started })
+    }
+
+    /// Indices of the services in the order they must be spawned.
+    fn order(&self) -> Result<Vec<usize>> {
+        let mut by_name: BTreeMap<&str, usize> = BTreeMap::new();
+        for (ix, service) in self.services.iter().enumerate() {
+            if by_name.insert(&service.name, ix).is_some() {
+                return Err(Error::DuplicateService {
+                    service: service.name.clone(),
+                });
+            }
+        }
+        let mut dependencies = Vec::with_capacity(self.services.len());
+        for service in self.services.iter() {
+            let mut deps = Vec::with_capacity(service.depends_on.len());
+            for dep in service.depends_on.iter() {
+                match by_name.get(dep.as_str()) {
+                    Some(ix) => deps.push(*ix),
+                    None => {
+                        return Err(Error::UnknownDependency {
+                            service: service.name.clone(),
+                            dependency: dep.clone(),
+                        })
+                    }
+                }
+            }
+            dependencies.push(deps);
+        }
+
+        let mut placed = vec![false; self.services.len()];
+        let mut order = Vec::with_capacity(self.services.len());
+        while order.len() < self.services.len() {
+            let next = (0..self.services.len())
+                .find(|ix| !placed[*ix] && dependencies[*ix].iter().all(|dep| placed[*dep]));
+            match next {
+                Some(ix) => {
+                    placed[ix] = true;
+                    order.push(ix);
+                }
+                None => {
+                    let services = (0..self.services.len())
+                        .filter(|ix| !placed[*ix])
+                        .map(|ix| self.services[ix].name.clone())
+                        .collect();
+                    return Err(Error::DependencyCycle { services });
+                }
+            }
+        }
+        Ok(order)
+    }
+}
+
+/// The services of a started `ServiceGraph`, in the order they were spawned.
+pub struct RunningGraph {
+    started: Vec<(String, ChildInfo)>,
+}
+
+impl RunningGraph {
+    /// The child spawned for the named service. If the service has been
+    /// restarted since, this is the original child, and
+    /// `ChildHandle::latest` is the running one.
+    pub fn get(&mut self, name: &str) -> Option<&mut ChildInfo> {
+        self.started
+            .iter_mut()
+            .find(|(n, _)| n == name)
+            .map(|(_, child)| child)
+    }
+
+    /// Stop the services in the reverse of the order they were started,
+    /// with their `SpawnOptions::stop` method, waiting for each to exit
+    /// before moving on. A restarted service is stopped in its latest child.
+    /// As with `Muxer::cleanup`, a failed stop method falls back to `SIGTERM`,
+    /// and a service without a `Stop::grace` is killed after 10 seconds.
+    ///
+    /// Restarts are disabled first, and a SIGINT escalates the shutdown as
+    /// with `Muxer::begin_shutdown`. If it is abandoned,
+    /// `Error::ShutdownAbandoned` is returned without waiting for the rest.
+    pub fn shutdown(self, muxer: &mut Muxer) -> Result<()> {
+        muxer.begin_shutdown();
+        for (name, child) in self.started.into_iter().rev() {
+            let handle = child.handle().latest();
+            if !handle.is_running() {
+                continue;
+            }
+            let pid = handle.pid();
+            if muxer.stop_child_within(pid, Some(CLEANUP_GRACE)).is_err() {
+                handle.terminate().map_err(|error| Error::ServiceFailed {
+                    service: name,
+                    error,
+                })?;
+            }
+            muxer.pump(|ev| match ev {
+                Event::ChildTerminated { pid: p, .. } if p == pid => Some(Ok(())),
+                Event::Shutdown {
+                    stage: ShutdownStage::Abandoned,
+                } => Some(Err(Error::ShutdownAbandoned)),
+                _ => None,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A graph of services named by the first word of each line, depending
+    /// on the services named by the rest.
+    fn service_graph(services: &[&str]) -> ServiceGraph {
+        let mut graph = ServiceGraph::new();
+        for line in services {
+            let mut words = line.split_whitespace();
+            let mut service = Service::new(words.next().unwrap(), Command::new("true"));
+            service.depends_on = words.map(str::to_owned).collect();
+            graph.add(service);
+        }
+        graph
+    }
+
+    fn names(graph: &ServiceGraph) -> Vec<&str> {
+        let order = graph.order().unwrap();
+        order
+            .iter()
+            .map(|ix| graph.services[*ix].name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn order_puts_dependencies_first() {
+        let graph = service_graph(&["web api assets", "api db", "assets", "db"]);
+        assert_eq!(names(&graph), ["assets", "db", "api", "web"]);
+    }
+
+    #[test]
+    fn order_keeps_independent_services_in_insertion_order() {
+        let graph = service_graph(&["c", "a", "b"]);
+        assert_eq!(names(&graph), ["c", "a", "b"]);
+        let graph = service_graph(&["b a", "c", "a"]);
+        assert_eq!(names(&graph), ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn order_rejects_duplicate_services() {
+        let graph = service_graph(&["db", "api db", "db"]);
+        assert!(matches!(
+            graph.order(),
+            Err(Error::DuplicateService { service }) if service == "db"
+        ));
+    }
+
+    #[test]
+    fn order_rejects_unknown_dependencies() {
+        let graph = service_graph(&["api db"]);
+        assert!(matches!(
+            graph.order(),
+            Err(Error::UnknownDependency { service, dependency })
+                if service == "api" && dependency == "db"
+        ));
+    }
+
+    #[test]
+    fn order_reports_the_services_left_by_a_cycle() {
+        let graph = service_graph(&["db", "a c", "b a", "c b", "d c"]);
+        assert!(matches!(
+            graph.order(),
+            Err(Error::DependencyCycle { services }) if services == ["a", "b", "c", "d"]
+        ));
+        let graph = service_graph(&["a a"]);
+        assert!(matches!(
+            graph.order(),
+            Err(Error::DependencyCycle { services }) if services == ["a"]
+        ));
+    }
+}
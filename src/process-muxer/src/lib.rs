@@ -1,17 +1,32 @@
 use std::{
     io::{self, stderr, stdout, LineWriter, Write},
-    os::unix::process::CommandExt,
-    path::{Path, PathBuf},
-    process::{Command, ExitStatus, Stdio},
+    path::PathBuf,
+    process::{Command, ExitStatus},
+    time::Duration,
 };
 
+mod graph;
+mod probe;
+
 use console::Style;
-pub use process_muxer_core::{ChildInfo, Event, FdTag, Pid, Signal};
+pub use graph::{Readiness, RunningGraph, Service, ServiceGraph};
+pub use probe::Probe;
+pub use process_muxer_core::{
+    Cgroup, ChildHandle, ChildInfo, ChildStatus, CoreDumps, CrashLoop, Deadline, Event, FdTag,
+    Focus, HealthCheck, Identity, Limit, Listener, Pid, Resource, ResourceLimits, Restart,
+    RestartPolicy, Retention, Rlimit, RunState, ShutdownStage, Signal, SignalRule, SignalTarget,
+    SpawnOptions, StdioMode, Stop, StopMethod, StreamStatus, TailLine, Termination, TimerId,
+    Watchdog, WatchdogAction,
+};
 use regex::Regex;
 
+/// How long `Muxer::cleanup` and `RunningGraph::shutdown` wait for a child
+/// without a `Stop::grace` before sending `SIGKILL`.
+pub(crate) const CLEANUP_GRACE: Duration = Duration::from_secs(10);
+
 pub trait MuxerHook {
     fn before_event<'a>(&mut self, event: &Event<'a>);
-    fn before_spawn(&mut self, command: &Command);
+    fn before_spawn(&mut self, command: &Command, options: &SpawnOptions);
 }
 
 pub struct Muxer {
@@ -44,49 +59,188 @@ impl Muxer {
         })
     }
 
-    /// Send SIGTERM to all children and wait for them to exit.
+    /// Configure how much the muxer remembers about exited children.
+    pub fn set_retention(&mut self, retention: Retention) {
+        self.inner.set_retention(retention);
+    }
+
+    /// A handle to the child with the given pid, if it is still running or
+    /// retained in the history.
+    pub fn child(&self, pid: Pid) -> Option<ChildHandle> {
+        self.inner.child(pid)
+    }
+
+    /// Handles to retained exited children, oldest first.
+    pub fn history(&self) -> impl Iterator<Item = &ChildHandle> {
+        self.inner.history()
+    }
+
+    /// A snapshot of every running child followed by the retained exited
+    /// children.
+    pub fn status(&self) -> Vec<ChildStatus> {
+        self.inner.status()
+    }
+
+    /// Handles to all children that have not yet been reaped.
+    pub fn handles(&self) -> impl Iterator<Item = ChildHandle> + '_ {
+        self.inner.handles()
+    }
+
+    /// Emit `Event::TimerFired` from `pump` once `delay` has passed.
+    pub fn set_timer(&mut self, delay: Duration) -> TimerId {
+        self.inner.set_timer(delay)
+    }
+
+    /// Cancel a timer that has not fired yet.
+    pub fn cancel_timer(&mut self, id: TimerId) -> bool {
+        self.inner.cancel_timer(id)
+    }
+
+    /// Terminate a child and spawn it again once it exits, even if its
+    /// `RestartPolicy` would not restart it.
+    pub fn restart_child(&mut self, pid: Pid) -> io::Result<()> {
+        self.inner.restart_child(pid)
+    }
+
+    /// Forward lines read from stdin to the children in focus, which must be
+    /// spawned with `SpawnOptions::stdin_input`. See
+    /// `process_muxer_core::Muxer::forward_stdin` for the focus commands.
+    pub fn forward_stdin(&mut self, command_prefix: impl Into<String>) -> io::Result<()> {
+        self.inner.forward_stdin(command_prefix)
+    }
+
+    pub fn set_focus(&mut self, focus: Focus) {
+        self.inner.set_focus(focus)
+    }
+
+    pub fn focus(&self) -> Option<&Focus> {
+        self.inner.focus()
+    }
+
+    /// Forward signals the muxer receives to children, see `SignalRule`.
+    pub fn add_signal_rule(&mut self, rule: SignalRule) -> io::Result<()> {
+        self.inner.add_signal_rule(rule)
+    }
+
+    pub fn clear_signal_rules(&mut self) {
+        self.inner.clear_signal_rules()
+    }
+
+    /// Give the terminal back to a stopped `control` child and continue it.
+    pub fn resume_foreground(&mut self, pid: Pid) -> io::Result<()> {
+        self.inner.resume_foreground(pid)
+    }
+
+    /// Ask a child to exit with its `SpawnOptions::stop` method.
+    pub fn stop_child(&mut self, pid: Pid) -> io::Result<()> {
+        self.inner.stop_child(pid)
+    }
+
+    /// Like `stop_child`, with a grace period for children that have none.
+    pub fn stop_child_within(
+        &mut self,
+        pid: Pid,
+        default_grace: Option<Duration>,
+    ) -> io::Result<()> {
+        self.inner.stop_child_within(pid, default_grace)
+    }
+
+    /// Start a shutdown, escalated by SIGINT. See
+    /// `process_muxer_core::Muxer::begin_shutdown`.
+    pub fn begin_shutdown(&mut self) {
+        self.inner.begin_shutdown()
+    }
+
+    pub fn shutdown_stage(&self) -> Option<ShutdownStage> {
+        self.inner.shutdown_stage()
+    }
+
+    /// Allow or forbid restarting children according to their
+    /// `RestartPolicy`.
+    pub fn set_restarts_enabled(&mut self, enabled: bool) {
+        self.inner.set_restarts_enabled(enabled);
+    }
+
+    /// Stop all children with their `SpawnOptions::stop` method, or SIGTERM
+    /// if it fails, and wait for them to exit. Children still running after
+    /// their `Stop::grace`, or 10 seconds without one, are sent SIGKILL.
+    /// Pending and future restarts are cancelled first. This is a shutdown as
+    /// started by `begin_shutdown`: a SIGINT meanwhile sends SIGKILL to the
+    /// remaining children, and another one abandons the shutdown, returning
+    /// an `Interrupted` error without waiting. The caller should then exit.
     pub fn cleanup(&mut self) -> io::Result<()> {
-        let mut child_count = 0;
-        for pid in self.inner.pids() {
-            child_count += 1;
-            unsafe { libc::kill(pid.inner as i32, libc::SIGTERM) };
+        self.inner.begin_shutdown();
+        let handles: Vec<ChildHandle> = self.inner.handles().collect();
+        let mut child_count = handles.len();
+        for handle in handles {
+            if self
+                .stop_child_within(handle.pid(), Some(CLEANUP_GRACE))
+                .is_err()
+            {
+                handle.terminate()?;
+            }
         }
 
         if child_count > 0 {
-            self.pump(|ev| {
-                if let Event::ChildTerminated { .. } = ev {
+            return self.pump(|ev| match ev {
+                Event::ChildTerminated { .. } => {
                     child_count -= 1;
-                    if child_count == 0 {
-                        return Some(());
-                    }
+                    (child_count == 0).then_some(Ok(()))
                 }
-                None
+                Event::Shutdown {
+                    stage: ShutdownStage::Abandoned,
+                } => Some(Err(io::Error::new(
+                    io::ErrorKind::Interrupted,
+                    "the shutdown was abandoned",
+                ))),
+                _ => None,
             });
         }
         Ok(())
     }
 
     /// Create a new child process that inherits stdin, stdout, and stderr and
-    /// is a member of the same process group.
+    /// runs as a foreground job: it gets a process group of its own and the
+    /// terminal, which the muxer takes back when the child stops or exits.
+    /// Without a terminal, the child shares the muxer's process group.
     pub fn control(&mut self, cmd: Command) -> io::Result<ChildInfo> {
-        let child = self.spawn(cmd)?;
+        self.control_with(cmd, SpawnOptions::default())
+    }
+
+    /// Like `control`, with muxer-specific spawn options such as a label.
+    pub fn control_with(
+        &mut self,
+        cmd: Command,
+        mut options: SpawnOptions,
+    ) -> io::Result<ChildInfo> {
+        options.foreground = true;
+        let child = self.spawn(cmd, options)?;
         Ok(child)
     }
 
-    pub fn forward(&mut self, mut cmd: Command) -> io::Result<ChildInfo> {
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-        cmd.process_group(0);
-        let child = self.spawn(cmd)?;
+    pub fn forward(&mut self, cmd: Command) -> io::Result<ChildInfo> {
+        self.forward_with(cmd, SpawnOptions::default())
+    }
+
+    /// Like `forward`, with muxer-specific spawn options such as a label.
+    pub fn forward_with(
+        &mut self,
+        cmd: Command,
+        mut options: SpawnOptions,
+    ) -> io::Result<ChildInfo> {
+        options.stdout = StdioMode::Piped;
+        options.stderr = StdioMode::Piped;
+        options.process_group.get_or_insert(0);
+        let child = self.spawn(cmd, options)?;
         Ok(child)
     }
 
-    fn spawn(&mut self, cmd: Command) -> io::Result<ChildInfo> {
+    fn spawn(&mut self, cmd: Command, options: SpawnOptions) -> io::Result<ChildInfo> {
         for hook in self.hooks.iter_mut() {
-            hook.before_spawn(&cmd);
+            hook.before_spawn(&cmd, &options);
         }
 
-        self.inner.spawn(cmd)
+        self.inner.spawn_with(cmd, options)
     }
 
     pub fn wait_for_signal(&mut self) -> Signal {
@@ -102,6 +256,7 @@ impl Muxer {
         if let Some(exit_status) = child_info.exit_status() {
             return Err(Error::UnexpectedChildTermination {
                 pid: child_info.pid,
+                label: child_info.label().to_owned(),
                 prog_path: PathBuf::from(child_info.program()),
                 exit_status,
             });
@@ -109,10 +264,13 @@ impl Muxer {
         self.pump(|ev| match ev {
             ChildTerminated {
                 pid,
+                label,
                 exit_status,
                 prog_path,
+                ..
             } if pid == child_info.pid => Some(Err(Error::UnexpectedChildTermination {
                 pid,
+                label: label.to_owned(),
                 prog_path: PathBuf::from(prog_path),
                 exit_status,
             })),
@@ -127,6 +285,79 @@ impl Muxer {
         })
     }
 
+    /// Check `probe` every `interval` until it accepts a connection. Fails if
+    /// the child exits first.
+    pub fn wait_until_listening(
+        &mut self,
+        child_info: &ChildInfo,
+        probe: &Probe,
+        interval: Duration,
+    ) -> Result<()> {
+        use Event::*;
+        loop {
+            if let Some(exit_status) = child_info.exit_status() {
+                return Err(Error::UnexpectedChildTermination {
+                    pid: child_info.pid,
+                    label: child_info.label().to_owned(),
+                    prog_path: PathBuf::from(child_info.program()),
+                    exit_status,
+                });
+            }
+            if probe.is_listening() {
+                return Ok(());
+            }
+            let timer = self.set_timer(interval);
+            let res = self.pump(|ev| match ev {
+                TimerFired { id } if id == timer => Some(Ok(())),
+                // termination is reported at the top of the loop
+                ChildTerminated { pid, .. } if pid == child_info.pid => Some(Ok(())),
+                SignalReceived { signal } => Some(Err(Error::from(signal))),
+                _ => None,
+            });
+            self.cancel_timer(timer);
+            res?;
+        }
+    }
+
+    /// Wait for a child spawned with `SpawnOptions::notify` to send
+    /// `READY=1`. Fails if the child exits first.
+    pub fn wait_until_ready(&mut self, child_info: &ChildInfo) -> Result<()> {
+        use Event::*;
+        if child_info.handle().is_ready() {
+            return Ok(());
+        }
+        if let Some(exit_status) = child_info.exit_status() {
+            return Err(Error::UnexpectedChildTermination {
+                pid: child_info.pid,
+                label: child_info.label().to_owned(),
+                prog_path: PathBuf::from(child_info.program()),
+                exit_status,
+            });
+        }
+        self.pump(|ev| match ev {
+            ChildNotified { pid, fields, .. }
+                if pid == child_info.pid
+                    && fields.iter().any(|(k, v)| k == "READY" && v == "1") =>
+            {
+                Some(Ok(()))
+            }
+            ChildTerminated {
+                pid,
+                label,
+                exit_status,
+                prog_path,
+                ..
+            } if pid == child_info.pid => Some(Err(Error::UnexpectedChildTermination {
+                pid,
+                label: label.to_owned(),
+                prog_path: PathBuf::from(prog_path),
+                exit_status,
+            })),
+            SignalReceived { signal } => Some(Err(Error::from(signal))),
+            _ => None,
+        })
+    }
+
     pub fn wait(&mut self, child_info: &ChildInfo) -> Result<ExitStatus> {
         use Event::*;
         if let Some(exit_status) = child_info.exit_status() {
@@ -146,12 +377,31 @@ impl Muxer {
 pub enum Error {
     UnexpectedChildTermination {
         pid: Pid,
+        label: String,
         prog_path: PathBuf,
         exit_status: ExitStatus,
     },
     UnexpectedSignal {
         signal: Signal,
     },
+    /// A `ServiceGraph` service could not be spawned or signalled.
+    ServiceFailed {
+        service: String,
+        error: io::Error,
+    },
+    UnknownDependency {
+        service: String,
+        dependency: String,
+    },
+    /// Two services of a `ServiceGraph` have the same name.
+    DuplicateService {
+        service: String,
+    },
+    DependencyCycle {
+        services: Vec<String>,
+    },
+    /// A second SIGINT abandoned the shutdown of a `RunningGraph`.
+    ShutdownAbandoned,
 }
 
 pub type Result<A> = std::result::Result<A, Error>;
@@ -182,30 +432,77 @@ impl PrintInfo<LineWriter<io::Stdout>, LineWriter<io::Stderr>> {
     }
 }
 
+impl Default for PrintInfo<LineWriter<io::Stdout>, LineWriter<io::Stderr>> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<Stdout: Write, Stderr: Write> MuxerHook for PrintInfo<Stdout, Stderr> {
     fn before_event<'a>(&mut self, ev: &Event<'a>) {
         match ev {
             Event::ChildTerminated {
-                prog_path,
+                label,
                 exit_status,
+                runtime,
+                core_dump,
                 ..
             } => {
+                let termination = match Termination::from(*exit_status) {
+                    Termination::Exited(code) => format!("exited with code {code}"),
+                    Termination::Signaled(signal, false) => format!("killed by {signal}"),
+                    Termination::Signaled(signal, true) => {
+                        format!("killed by {signal} (core dumped)")
+                    }
+                };
                 writeln!(
                     &mut self.stdout,
                     "{}{} {} {}{}",
                     self.info_style.apply_to("["),
-                    self.info_style.apply_to(prog_path.display()),
-                    self.info_style.apply_to("terminated with"),
-                    self.info_style.apply_to(exit_status),
+                    self.info_style.apply_to(label),
+                    self.info_style.apply_to(termination),
+                    self.info_style
+                        .apply_to(format!("after {}", format_runtime(*runtime))),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+                if let Some(core_dump) = core_dump {
+                    writeln!(
+                        &mut self.stdout,
+                        "{}{} {}{}",
+                        self.info_style.apply_to("["),
+                        self.info_style.apply_to(label),
+                        self.info_style
+                            .apply_to(format!("dumped core to {}", core_dump.display())),
+                        self.info_style.apply_to("]"),
+                    )
+                    .unwrap();
+                }
+            }
+            Event::ChildStopped { label, signal, .. } => {
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(label),
+                    self.info_style.apply_to(format!("stopped by {signal}")),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::ChildContinued { label, .. } => {
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(label),
+                    self.info_style.apply_to("continued"),
                     self.info_style.apply_to("]"),
                 )
                 .unwrap();
             }
             Event::ChildWrote {
-                prog_path,
-                tag,
-                line,
-                ..
+                label, tag, line, ..
             } => {
                 let forward_style = match tag {
                     FdTag::Stdout => &self.stdout_style,
@@ -219,13 +516,13 @@ impl<Stdout: Write, Stderr: Write> MuxerHook for PrintInfo<Stdout, Stderr> {
                     output,
                     "{}{}{} {}",
                     self.info_style.apply_to("["),
-                    self.info_style.apply_to(&prog_path.display()),
+                    self.info_style.apply_to(label),
                     self.info_style.apply_to("]"),
                     forward_style.apply_to(line),
                 )
                 .unwrap();
             }
-            Event::FdClosed { prog_path, tag, .. } => {
+            Event::FdClosed { label, tag, .. } => {
                 let handle: &str = match tag {
                     FdTag::Stderr => "stderr",
                     FdTag::Stdout => "stdout",
@@ -234,18 +531,237 @@ impl<Stdout: Write, Stderr: Write> MuxerHook for PrintInfo<Stdout, Stderr> {
                     &mut self.stdout,
                     "{}{} {} {}{}",
                     self.info_style.apply_to("["),
-                    self.info_style.apply_to(prog_path.display()),
+                    self.info_style.apply_to(label),
                     self.info_style.apply_to("closed"),
                     self.info_style.apply_to(handle),
                     self.info_style.apply_to("]"),
                 )
                 .unwrap();
             }
+            Event::ChildRestarting {
+                label,
+                attempt,
+                delay,
+                ..
+            } => {
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(label),
+                    self.info_style.apply_to(format!("restarting in {delay:?}")),
+                    self.info_style.apply_to(format!("(attempt {attempt})")),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::ChildRestarted { label, pid, .. } => {
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(label),
+                    self.info_style.apply_to("restarted as pid"),
+                    self.info_style.apply_to(pid),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::RestartFailed { label, error, .. } => {
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(label),
+                    self.info_style.apply_to("failed to restart:"),
+                    self.info_style.apply_to(error),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::CrashLoop {
+                label,
+                failures,
+                stderr,
+                ..
+            } => {
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(label),
+                    self.info_style.apply_to(format!(
+                        "is crash-looping after {failures} failures, not restarting"
+                    )),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+                for line in stderr.iter() {
+                    writeln!(
+                        &mut self.stderr,
+                        "{}{}{} {}",
+                        self.info_style.apply_to("["),
+                        self.info_style.apply_to(label),
+                        self.info_style.apply_to("]"),
+                        self.stderr_style.apply_to(line),
+                    )
+                    .unwrap();
+                }
+            }
+            Event::HealthChanged { label, healthy, .. } => {
+                let health = match healthy {
+                    true => "is healthy again",
+                    false => "is unhealthy",
+                };
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(label),
+                    self.info_style.apply_to(health),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::ChildNotified { label, fields, .. } => {
+                for (key, value) in fields.iter() {
+                    let message = match key.as_str() {
+                        "READY" if value == "1" => "is ready".to_owned(),
+                        "STATUS" => format!("status: {value}"),
+                        _ => continue,
+                    };
+                    writeln!(
+                        &mut self.stdout,
+                        "{}{} {}{}",
+                        self.info_style.apply_to("["),
+                        self.info_style.apply_to(label),
+                        self.info_style.apply_to(message),
+                        self.info_style.apply_to("]"),
+                    )
+                    .unwrap();
+                }
+            }
+            Event::WatchdogExpired { label, silent, .. } => {
+                let silent = Duration::from_millis(silent.as_millis() as u64);
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(label),
+                    self.info_style
+                        .apply_to(format!("has been silent for {silent:?}")),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::DeadlineExceeded { label, runtime, .. } => {
+                let runtime = Duration::from_millis(runtime.as_millis() as u64);
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(label),
+                    self.info_style
+                        .apply_to(format!("exceeded its deadline after {runtime:?}")),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::ResourceSample { .. } => (),
+            Event::ResourceLimitExceeded {
+                label, resource, ..
+            } => {
+                let resource = match resource {
+                    Resource::Memory => "memory",
+                    Resource::Cpu => "CPU",
+                };
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(label),
+                    self.info_style
+                        .apply_to(format!("killed for exceeding its {resource} limit")),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::OomKilled { label, kills, .. } => {
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(label),
+                    self.info_style.apply_to(format!(
+                        "had {kills} process(es) killed for running out of memory"
+                    )),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::FocusChanged { focus } => {
+                let focus = match focus {
+                    Focus::None => "stdin is not forwarded".to_owned(),
+                    Focus::Child(label) => format!("stdin goes to {label}"),
+                    Focus::All => "stdin goes to all children".to_owned(),
+                };
+                writeln!(
+                    &mut self.stdout,
+                    "{}{}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(focus),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::StopCommandFailed {
+                label, exit_status, ..
+            } => {
+                let failure = match exit_status.map(Termination::from) {
+                    Some(Termination::Exited(code)) => {
+                        format!("stop command exited with code {code}")
+                    }
+                    Some(Termination::Signaled(signal, _)) => {
+                        format!("stop command was killed by {signal}")
+                    }
+                    None => "stop command did not finish and was killed".to_owned(),
+                };
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(label),
+                    self.info_style.apply_to(failure),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::StdinUnrouted { line, label } => {
+                let message = match label {
+                    Some(label) => format!("{label} is not reading input, dropped:"),
+                    None => "no child took input:".to_owned(),
+                };
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(message),
+                    self.info_style.apply_to(line),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::TimerFired { .. } => (),
             Event::SignalReceived { ref signal } => {
+                let other;
                 let signal = match signal {
                     Signal::Hangup => "hangup (SIGHUP)",
                     Signal::Interrupt => "interrupt (SIGINT)",
                     Signal::Terminate => "terminate (SIGTERM)",
+                    signal => {
+                        other = signal.to_string();
+                        &other
+                    }
                 };
                 writeln!(
                     &mut self.stdout,
@@ -257,18 +773,63 @@ impl<Stdout: Write, Stderr: Write> MuxerHook for PrintInfo<Stdout, Stderr> {
                 )
                 .unwrap();
             }
+            Event::Shutdown { stage } => {
+                let message = match stage {
+                    ShutdownStage::Graceful => {
+                        "shutting down, interrupt again to kill all children"
+                    }
+                    ShutdownStage::Forced => {
+                        "killed all children, interrupt again to stop waiting for them"
+                    }
+                    ShutdownStage::Abandoned => "no longer waiting for children",
+                };
+                writeln!(
+                    &mut self.stdout,
+                    "{}{}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(message),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
+            Event::SignalForwarded { label, signal, .. } => {
+                writeln!(
+                    &mut self.stdout,
+                    "{}{} {}{}",
+                    self.info_style.apply_to("["),
+                    self.info_style.apply_to(format!("sent {signal} to")),
+                    self.info_style.apply_to(label),
+                    self.info_style.apply_to("]"),
+                )
+                .unwrap();
+            }
         }
     }
 
-    fn before_spawn(&mut self, cmd: &Command) {
-        let prog_path: &Path = Path::new(cmd.get_program());
+    fn before_spawn(&mut self, cmd: &Command, options: &SpawnOptions) {
+        let prog_path = PathBuf::from(cmd.get_program());
+        let name = match &options.label {
+            Some(label) => format!("{label} ({})", prog_path.display()),
+            None => prog_path.display().to_string(),
+        };
         writeln!(
             &mut self.stdout,
             "{} {}{}",
             self.info_style.apply_to("[Running"),
-            self.info_style.apply_to(prog_path.display()),
+            self.info_style.apply_to(name),
             self.info_style.apply_to("]")
         )
         .unwrap();
     }
 }
+
+/// Format a child's runtime for display, e.g. "350ms", "4.2s" or "3m12s".
+fn format_runtime(runtime: Duration) -> String {
+    let secs = runtime.as_secs();
+    match secs {
+        0 => format!("{}ms", runtime.as_millis()),
+        1..=59 => format!("{:.1}s", runtime.as_secs_f64()),
+        60..=3599 => format!("{}m{:02}s", secs / 60, secs % 60),
+        _ => format!("{}h{:02}m{:02}s", secs / 3600, secs / 60 % 60, secs % 60),
+    }
+}
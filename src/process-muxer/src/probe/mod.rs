@@ -0,0 +1,26 @@
+use std::{
+    net::{SocketAddr, TcpStream},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+/// A local endpoint a child is expected to listen on once it is ready.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Probe {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl Probe {
+    /// How long a single TCP connection attempt may take.
+    const CONNECT_TIMEOUT: Duration = Duration::from_millis(250);
+
+    /// Whether a connection can be made right now.
+    pub fn is_listening(&self) -> bool {
+        match self {
+            Probe::Tcp(addr) => TcpStream::connect_timeout(addr, Self::CONNECT_TIMEOUT).is_ok(),
+            Probe::Unix(path) => UnixStream::connect(path).is_ok(),
+        }
+    }
+}